@@ -1,16 +1,23 @@
+use std::convert::Infallible;
 use std::str::FromStr;
+use std::sync::Mutex;
 use std::time::Duration;
 
 use alloy::dyn_abi::SolType;
 use alloy::network::EthereumWallet;
-use alloy::primitives::{fixed_bytes, Address, Bytes, I256, U256};
-use alloy::providers::ProviderBuilder;
+use alloy::primitives::{fixed_bytes, Address, Bytes, TxHash, I256, U256};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::TransactionRequest;
 use alloy::signers::local::PrivateKeySigner;
 use alloy::sol;
+use alloy::transports::TransportError;
 use orchestrator_utils::env_utils::get_env_var_or_panic;
+use starknet::core::types::Felt;
 use tokio::time::sleep;
 use url::Url;
 
+use crate::utils::{wait_for, WaitError, WaitOpts};
+
 sol!(
     #[allow(missing_docs)]
     #[sol(rpc)]
@@ -41,14 +48,67 @@ sol!(
     "artifacts/contracts/GPSVerifier.json"
 );
 
+#[derive(Debug, thiserror::Error)]
+pub enum AnvilError {
+    #[error("Anvil RPC call {method} failed: {source}")]
+    Rpc { method: &'static str, source: TransportError },
+    #[error("Starknet core contract call {method} failed: {source}")]
+    Call { method: &'static str, source: alloy::contract::Error },
+    #[error("Starknet core contract has no state yet, its block number is {0}")]
+    NoStarknetState(I256),
+    #[error("Starknet state did not reach block {block_n} in {timeout:?}, it is at block {current:?}")]
+    Timeout { block_n: u64, timeout: Duration, current: Option<u64> },
+    #[error("Anvil RPC at {url} did not answer eth_chainId in {timeout:?}: {source}")]
+    Unresponsive { url: Url, timeout: Duration, source: TransportError },
+}
+
 pub struct AnvilSetup {
     pub rpc_url: Url,
+    chain_id: Option<u64>,
 }
 
 impl AnvilSetup {
     pub fn new() -> Self {
         let rpc_url = Url::from_str("http://localhost:8545").unwrap();
-        Self { rpc_url }
+        Self { rpc_url, chain_id: None }
+    }
+
+    /// Waits until the Anvil RPC answers `eth_chainId`, and remembers the chain id. Anvil may accept connections and
+    /// still fail every request, for instance when it was started with a bad fork url.
+    pub async fn wait_till_ready(&mut self, timeout: Duration) -> Result<u64, AnvilError> {
+        let provider = ProviderBuilder::new().on_http(self.rpc_url.clone());
+        let last_error = Mutex::new(None);
+        let res = wait_for(
+            || async {
+                match provider.get_chain_id().await {
+                    Ok(chain_id) => Ok::<_, Infallible>(Some(chain_id)),
+                    Err(err) => {
+                        *last_error.lock().expect("Poisoned lock") = Some(err);
+                        Ok(None)
+                    }
+                }
+            },
+            WaitOpts::new(timeout, Duration::from_millis(500)),
+        )
+        .await;
+
+        match res {
+            Ok(chain_id) => {
+                self.chain_id = Some(chain_id);
+                Ok(chain_id)
+            }
+            Err(WaitError::Failed(never)) => match never {},
+            Err(WaitError::Timeout(_)) => Err(AnvilError::Unresponsive {
+                url: self.rpc_url.clone(),
+                timeout,
+                source: last_error.into_inner().expect("Poisoned lock").expect("eth_chainId was called at least once"),
+            }),
+        }
+    }
+
+    /// Chain id reported by Anvil, once [`Self::wait_till_ready`] succeeded.
+    pub fn chain_id(&self) -> Option<u64> {
+        self.chain_id
     }
 
     pub async fn deploy_contracts(&self) -> (Address, Address) {
@@ -106,6 +166,78 @@ impl AnvilSetup {
         println!("📦 Contract setup done. Txn Hash : {}", tx_hash);
         (*starknet_core_contract_client.address(), *verifier_client.address())
     }
+
+    /// Lets transactions be sent from `addr` without its private key, until [`Self::stop_impersonating`] is called.
+    pub async fn impersonate(&self, addr: Address) -> Result<(), AnvilError> {
+        self.raw_request("anvil_impersonateAccount", (addr,)).await
+    }
+
+    pub async fn stop_impersonating(&self, addr: Address) -> Result<(), AnvilError> {
+        self.raw_request("anvil_stopImpersonatingAccount", (addr,)).await
+    }
+
+    /// Sends `tx` as-is through `eth_sendTransaction`, without signing it locally. Anvil signs it on behalf of
+    /// `tx.from`, which must be an unlocked or impersonated account.
+    pub async fn send_raw(&self, tx: TransactionRequest) -> Result<TxHash, AnvilError> {
+        self.raw_request("eth_sendTransaction", (tx,)).await
+    }
+
+    /// Block number and state root of the last Starknet state update accepted by the core contract.
+    pub async fn read_starknet_state(&self, core_contract: Address) -> Result<(u64, Felt), AnvilError> {
+        let provider = ProviderBuilder::new().on_http(self.rpc_url.clone());
+        let contract = StarknetCoreContract::new(core_contract, provider);
+
+        let block_number = contract
+            .stateBlockNumber()
+            .call()
+            .await
+            .map_err(|source| AnvilError::Call { method: "stateBlockNumber", source })?
+            ._0;
+        let state_root =
+            contract.stateRoot().call().await.map_err(|source| AnvilError::Call { method: "stateRoot", source })?._0;
+
+        let block_number = u64::try_from(block_number).map_err(|_| AnvilError::NoStarknetState(block_number))?;
+        Ok((block_number, Felt::from_bytes_be(&state_root.to_be_bytes::<32>())))
+    }
+
+    /// Waits until the core contract has accepted a state update for `block_n` or a later block, and returns that
+    /// state.
+    pub async fn wait_for_state_block(
+        &self,
+        core_contract: Address,
+        block_n: u64,
+        timeout: Duration,
+    ) -> Result<(u64, Felt), AnvilError> {
+        let res = wait_for(
+            || async move {
+                match self.read_starknet_state(core_contract).await {
+                    Ok(state) if state.0 >= block_n => Ok(Some(state)),
+                    Ok(_) | Err(AnvilError::NoStarknetState(_)) => Ok(None),
+                    Err(err) => Err(err),
+                }
+            },
+            WaitOpts::new(timeout, Duration::from_secs(1)),
+        )
+        .await;
+
+        match res {
+            Ok(state) => Ok(state),
+            Err(WaitError::Failed(err)) => Err(err),
+            Err(WaitError::Timeout(_)) => {
+                let current = self.read_starknet_state(core_contract).await.ok().map(|(current, _)| current);
+                Err(AnvilError::Timeout { block_n, timeout, current })
+            }
+        }
+    }
+
+    async fn raw_request<P, R>(&self, method: &'static str, params: P) -> Result<R, AnvilError>
+    where
+        P: serde::Serialize + Clone + Send + Sync,
+        R: serde::de::DeserializeOwned + Send + Sync + Unpin + 'static,
+    {
+        let provider = ProviderBuilder::new().on_http(self.rpc_url.clone());
+        provider.raw_request(method.into(), params).await.map_err(|source| AnvilError::Rpc { method, source })
+    }
 }
 
 impl Default for AnvilSetup {
@@ -7,19 +7,71 @@ pub mod starknet_client;
 pub mod utils;
 
 use std::net::TcpListener;
+use std::sync::atomic::{AtomicU16, Ordering};
 
-pub use mongodb::MongoDbServer;
+pub use mongodb::{MongoDbServer, MongoError};
 pub use node::Orchestrator;
 
 const MIN_PORT: u16 = 49_152;
 const MAX_PORT: u16 = 65_535;
 
-fn get_free_port() -> u16 {
-    for port in MIN_PORT..=MAX_PORT {
+/// Next port to try. Ports are handed out in turn so that services started concurrently from the same test process
+/// never get the same port.
+static NEXT_PORT: AtomicU16 = AtomicU16::new(MIN_PORT);
+
+/// A port bound on localhost, so that no other process can take it until the service it is meant for is started.
+/// Drop the reservation right before spawning the service to release the port: anything slow, such as building the
+/// service, should happen while the port is still reserved.
+pub struct PortReservation {
+    listener: TcpListener,
+}
+
+impl PortReservation {
+    pub fn port(&self) -> u16 {
+        self.listener.local_addr().expect("No local addr").port()
+    }
+}
+
+/// Reserves a free port of the dynamic range.
+pub fn reserve_port() -> PortReservation {
+    for _ in MIN_PORT..=MAX_PORT {
+        let port = NEXT_PORT
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |port| {
+                Some(if port == MAX_PORT { MIN_PORT } else { port + 1 })
+            })
+            .expect("Closure always returns Some");
         if let Ok(listener) = TcpListener::bind(("127.0.0.1", port)) {
-            return listener.local_addr().expect("No local addr").port();
+            return PortReservation { listener };
         }
         // otherwise port is occupied
     }
     panic!("No free ports available");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    #[test]
+    fn reserve_port_concurrently() {
+        const THREADS: usize = 32;
+        let barrier = Arc::new(Barrier::new(THREADS));
+        let handles = (0..THREADS)
+            .map(|_| {
+                let barrier = Arc::clone(&barrier);
+                thread::spawn(move || {
+                    barrier.wait();
+                    reserve_port()
+                })
+            })
+            .collect::<Vec<_>>();
+        // Keep every reservation alive until all the ports are compared.
+        let reservations = handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Vec<_>>();
+
+        let ports = reservations.iter().map(PortReservation::port).collect::<HashSet<_>>();
+        assert_eq!(ports.len(), THREADS);
+    }
+}
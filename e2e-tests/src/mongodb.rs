@@ -1,7 +1,14 @@
+use mongodb::bson::Document;
+use mongodb::options::ClientOptions;
 use orchestrator::types::params::database::DatabaseArgs;
 use url::Url;
 
-#[allow(dead_code)]
+#[derive(Debug, thiserror::Error)]
+pub enum MongoError {
+    #[error("MongoDB request to {endpoint} failed: {source}")]
+    ConnectionFailed { endpoint: Url, source: mongodb::error::Error },
+}
+
 pub struct MongoDbServer {
     endpoint: Url,
 }
@@ -14,4 +21,32 @@ impl MongoDbServer {
     pub fn endpoint(&self) -> Url {
         self.endpoint.clone()
     }
+
+    /// Drops a database with all its collections, so that the next scenario starts from an empty state without
+    /// restarting the container. Dropping a database which does not exist succeeds.
+    pub async fn drop_database(&self, name: &str) -> Result<(), MongoError> {
+        let client = self.client().await?;
+        client.database(name).drop(None).await.map_err(|source| self.connection_failed(source))
+    }
+
+    /// Number of documents in a collection, for instance the jobs created by the orchestrator.
+    pub async fn count_documents(&self, db: &str, collection: &str) -> Result<u64, MongoError> {
+        let client = self.client().await?;
+        client
+            .database(db)
+            .collection::<Document>(collection)
+            .count_documents(None, None)
+            .await
+            .map_err(|source| self.connection_failed(source))
+    }
+
+    async fn client(&self) -> Result<mongodb::Client, MongoError> {
+        let options =
+            ClientOptions::parse(self.endpoint.as_str()).await.map_err(|source| self.connection_failed(source))?;
+        mongodb::Client::with_options(options).map_err(|source| self.connection_failed(source))
+    }
+
+    fn connection_failed(&self, source: mongodb::error::Error) -> MongoError {
+        MongoError::ConnectionFailed { endpoint: self.endpoint.clone(), source }
+    }
 }
@@ -1,17 +1,82 @@
+use std::collections::VecDeque;
 use std::io::{BufRead, BufReader};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, Once};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use strum_macros::Display;
 use tokio::net::TcpStream;
 use url::Url;
 
-use crate::get_free_port;
-use crate::utils::get_repository_root;
+use crate::reserve_port;
+use crate::utils::{get_repository_root, wait_for, WaitError, WaitOpts};
 
-const CONNECTION_ATTEMPTS: usize = 720;
-const CONNECTION_ATTEMPT_DELAY_MS: u64 = 1000;
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(720);
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_secs(1);
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(5);
+/// Number of stderr lines of a failed orchestrator setup included in the panic message.
+const SETUP_STDERR_TAIL_LINES: usize = 50;
+
+/// Process groups of the orchestrators which are still running.
+static PROCESS_GROUPS: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+static INSTALL_SIGNAL_HANDLER: Once = Once::new();
+static TEARING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// The orchestrators run in their own process group, so a Ctrl-C in the terminal does not reach them. This makes sure
+/// they are stopped along with the tests instead of being orphaned.
+fn install_signal_handler() {
+    INSTALL_SIGNAL_HANDLER.call_once(|| {
+        thread::spawn(|| {
+            let runtime =
+                tokio::runtime::Builder::new_current_thread().enable_all().build().expect("Failed to build runtime");
+            runtime.block_on(tokio::signal::ctrl_c()).expect("Failed to listen for Ctrl-C");
+            if TEARING_DOWN.swap(true, Ordering::SeqCst) {
+                return;
+            }
+            let process_groups = PROCESS_GROUPS.lock().expect("Poisoned lock").clone();
+            println!("Interrupted, stopping {} orchestrator process group(s)", process_groups.len());
+            stop_process_groups(&process_groups);
+            std::process::exit(130);
+        });
+    });
+}
+
+fn register_process_group(process_group: u32) {
+    install_signal_handler();
+    PROCESS_GROUPS.lock().expect("Poisoned lock").push(process_group);
+}
+
+fn unregister_process_group(process_group: u32) {
+    PROCESS_GROUPS.lock().expect("Poisoned lock").retain(|group| *group != process_group);
+}
+
+fn signal_process_group(signal: &str, process_group: u32) -> bool {
+    let process_group = format!("-{process_group}");
+    Command::new("kill").args(["-s", signal, "--", &process_group]).status().is_ok_and(|status| status.success())
+}
+
+/// Terminates the process groups, and kills those which are still alive after the shutdown grace period.
+fn stop_process_groups(process_groups: &[u32]) {
+    for group in process_groups {
+        signal_process_group("TERM", *group);
+    }
+    let termination_start = Instant::now();
+    while termination_start.elapsed() < SHUTDOWN_GRACE_PERIOD {
+        // Signal 0 only checks whether the group still exists.
+        if !process_groups.iter().any(|group| signal_process_group("0", *group)) {
+            return;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    for group in process_groups {
+        signal_process_group("KILL", *group);
+    }
+}
 
 #[derive(Debug)]
 pub struct Orchestrator {
@@ -21,9 +86,22 @@ pub struct Orchestrator {
 
 impl Drop for Orchestrator {
     fn drop(&mut self) {
-        let mut kill =
-            Command::new("kill").args(["-s", "TERM", &self.process.id().to_string()]).spawn().expect("Failed to kill");
-        kill.wait().expect("Failed to kill the process");
+        // The orchestrator runs in its own process group: signal the whole group so that the binary spawned by
+        // `cargo run` is stopped along with cargo.
+        let process_group = self.process.id();
+        signal_process_group("TERM", process_group);
+
+        let termination_start = Instant::now();
+        while termination_start.elapsed() < SHUTDOWN_GRACE_PERIOD {
+            if let Ok(Some(_)) = self.process.try_wait() {
+                unregister_process_group(process_group);
+                return;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        signal_process_group("KILL", process_group);
+        let _ = self.process.wait();
+        unregister_process_group(process_group);
     }
 }
 
@@ -38,6 +116,7 @@ impl Orchestrator {
     pub fn new(mode: OrchestratorMode, mut envs: Vec<(String, String)>) -> Option<Self> {
         let repository_root = &get_repository_root();
         let mut address = String::new();
+        let mut reservation = None;
         std::env::set_current_dir(repository_root).expect("Failed to change working directory");
 
         let is_run_mode = mode == OrchestratorMode::Run;
@@ -45,21 +124,20 @@ impl Orchestrator {
 
         println!("Running orchestrator in {} mode", mode_str);
 
+        // Use the prebuilt orchestrator binary when provided, otherwise build it with cargo first. Either way, the
+        // binary is spawned directly so that the reserved port is released as late as possible.
+        let binary_path = match std::env::var("ORCHESTRATOR_BIN") {
+            Ok(binary_path) => {
+                let binary_path = PathBuf::from(binary_path);
+                assert!(is_executable(&binary_path), "ORCHESTRATOR_BIN is not an executable file: {:?}", binary_path);
+                binary_path
+            }
+            Err(_) => build_orchestrator(repository_root),
+        };
+        let mut command = Command::new(binary_path);
+
         // Configure common command arguments
-        let mut command = Command::new("cargo");
-        command
-            .arg("run")
-            .arg("--release")
-            .arg("-p")
-            .arg("orchestrator")
-            .arg("--features")
-            .arg("testing")
-            .arg(mode_str)
-            .arg("--layer=l2")
-            .arg("--aws")
-            .arg("--aws-s3")
-            .arg("--aws-sqs")
-            .arg("--aws-sns");
+        command.arg(mode_str).arg("--layer=l2").arg("--aws").arg("--aws-s3").arg("--aws-sqs").arg("--aws-sns");
 
         // Add event bridge arg only for setup mode
         if is_run_mode {
@@ -68,23 +146,28 @@ impl Orchestrator {
             command.arg("--sharp");
             command.arg("--mongodb");
 
-            let port = get_free_port();
-            let addr = format!("127.0.0.1:{}", port);
-            envs.push(("MADARA_ORCHESTRATOR_PORT".to_string(), port.to_string()));
+            let port = reserve_port();
+            let addr = format!("127.0.0.1:{}", port.port());
+            envs.push(("MADARA_ORCHESTRATOR_PORT".to_string(), port.port().to_string()));
             address = addr;
+            reservation = Some(port);
 
             command.stdout(Stdio::piped()).stderr(Stdio::piped());
         } else {
             command.arg("--aws-event-bridge");
             command.arg("--event-bridge-type");
             command.arg("rule");
-            // For setup mode, inherit the stdio to show output directly
-            command.stdout(Stdio::inherit()).stderr(Stdio::inherit());
+            // For setup mode, inherit stdout to show output directly. Stderr is forwarded as well, and its last lines
+            // are kept to explain a failure.
+            command.stdout(Stdio::inherit()).stderr(Stdio::piped());
         }
 
-        command.current_dir(repository_root).envs(envs);
+        command.current_dir(repository_root).envs(envs).process_group(0);
 
+        // Release the port right before the orchestrator binds it
+        drop(reservation);
         let mut process = command.spawn().expect("Failed to start process");
+        register_process_group(process.id());
 
         if is_run_mode {
             let stdout = process.stdout.take().expect("Failed to capture stdout");
@@ -108,18 +191,33 @@ impl Orchestrator {
             });
             Some(Self { process, address })
         } else {
+            let stderr = process.stderr.take().expect("Failed to capture stderr");
+            let stderr_tail = thread::spawn(move || {
+                let mut tail = VecDeque::with_capacity(SETUP_STDERR_TAIL_LINES);
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    eprintln!("{}", line);
+                    if tail.len() == SETUP_STDERR_TAIL_LINES {
+                        tail.pop_front();
+                    }
+                    tail.push_back(line);
+                }
+                tail
+            });
+
             // Wait for the process to complete and get its exit status
             let status = process.wait().expect("Failed to wait for process");
-            if status.success() {
-                println!("Orchestrator cloud setup completed ✅");
-            } else {
-                // Get the exit code if available
-                if let Some(code) = status.code() {
-                    println!("Orchestrator cloud setup failed with exit code: {}", code);
-                } else {
-                    println!("Orchestrator cloud setup terminated by signal");
-                }
+            unregister_process_group(process.id());
+            let stderr_tail = stderr_tail.join().expect("Failed to read stderr");
+            if !status.success() {
+                let reason = match (status.code(), status.signal()) {
+                    (Some(code), _) => format!("failed with exit code {}", code),
+                    (None, Some(signal)) => format!("was killed by signal {}", signal),
+                    (None, None) => format!("failed with {}", status),
+                };
+                let stderr_tail = Vec::from(stderr_tail).join("\n");
+                panic!("Orchestrator cloud setup {}, last lines of stderr:\n{}", reason, stderr_tail);
             }
+            println!("Orchestrator cloud setup completed ✅");
             None
         }
     }
@@ -133,22 +231,65 @@ impl Orchestrator {
     }
 
     pub async fn wait_till_started(&mut self) {
-        let mut attempts = CONNECTION_ATTEMPTS;
-        loop {
-            match TcpStream::connect(&self.address).await {
-                Ok(_) => return,
-                Err(err) => {
-                    if let Some(status) = self.has_exited() {
-                        panic!("Orchestrator node exited early with {}", status);
-                    }
-                    if attempts == 0 {
-                        panic!("Failed to connect to {}: {}", self.address, err);
+        let address = self.address.clone();
+        let res = wait_for(
+            || {
+                let exited = self.has_exited();
+                let address = address.clone();
+                async move {
+                    match exited {
+                        Some(status) => Err(status),
+                        None => Ok(TcpStream::connect(&address).await.ok().map(|_| ())),
                     }
                 }
-            };
+            },
+            WaitOpts::new(CONNECTION_TIMEOUT, CONNECTION_ATTEMPT_DELAY),
+        )
+        .await;
 
-            attempts -= 1;
-            tokio::time::sleep(Duration::from_millis(CONNECTION_ATTEMPT_DELAY_MS)).await;
+        match res {
+            Ok(()) => {}
+            Err(WaitError::Failed(status)) => panic!("Orchestrator node exited early with {}", status),
+            Err(err @ WaitError::Timeout(_)) => panic!("Failed to connect to {}: {}", self.address, err),
         }
     }
 }
+
+/// Builds the orchestrator with cargo and returns the path to its binary.
+fn build_orchestrator(repository_root: &Path) -> PathBuf {
+    let status = Command::new("cargo")
+        .args(["build", "--release", "-p", "orchestrator", "--features", "testing"])
+        .current_dir(repository_root)
+        .status()
+        .expect("Failed to run cargo build");
+    assert!(status.success(), "Building the orchestrator failed with {}", status);
+    let target_dir = std::env::var_os("CARGO_TARGET_DIR")
+        .map(|target_dir| repository_root.join(target_dir))
+        .unwrap_or_else(|| repository_root.join("target"));
+    target_dir.join("release").join("orchestrator")
+}
+
+fn is_executable(path: &Path) -> bool {
+    path.metadata().is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, Permissions};
+
+    #[test]
+    fn is_executable_checks_file_and_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!is_executable(&dir.path().join("missing")));
+        assert!(!is_executable(dir.path()));
+
+        let file = dir.path().join("orchestrator");
+        fs::write(&file, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&file, Permissions::from_mode(0o644)).unwrap();
+        assert!(!is_executable(&file));
+
+        fs::set_permissions(&file, Permissions::from_mode(0o755)).unwrap();
+        assert!(is_executable(&file));
+    }
+}
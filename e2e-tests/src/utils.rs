@@ -1,10 +1,13 @@
 use std::fs::File;
+use std::future::Future;
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use mongodb::bson::doc;
 use mongodb::options::{ClientOptions, ServerApi, ServerApiVersion};
 use starknet::core::types::StateUpdate;
+use tokio::time::Instant;
 
 use crate::MongoDbServer;
 
@@ -48,3 +51,55 @@ pub fn vec_u8_to_hex_string(data: &[u8]) -> String {
         format!("0x{}", new_hex_chars)
     }
 }
+
+/// How [`wait_for`] polls its condition.
+#[derive(Debug, Clone, Copy)]
+pub struct WaitOpts {
+    pub timeout: Duration,
+    /// Delay between the first two checks.
+    pub interval: Duration,
+    /// The delay is multiplied by this factor after every check, `1.0` keeps it fixed.
+    pub backoff: f64,
+    /// Upper bound of the delay between two checks.
+    pub max_interval: Duration,
+}
+
+impl WaitOpts {
+    /// Checks the condition at a fixed interval.
+    pub fn new(timeout: Duration, interval: Duration) -> Self {
+        Self { timeout, interval, backoff: 1.0, max_interval: interval }
+    }
+
+    pub fn backoff(self, backoff: f64, max_interval: Duration) -> Self {
+        Self { backoff, max_interval, ..self }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WaitError<E> {
+    #[error("Condition not met after {0:?}")]
+    Timeout(Duration),
+    #[error("Failed to check condition: {0}")]
+    Failed(E),
+}
+
+/// Calls `f` until it returns a value, an error, or the timeout expires.
+pub async fn wait_for<T, E, F, Fut>(mut f: F, opts: WaitOpts) -> Result<T, WaitError<E>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Option<T>, E>>,
+{
+    let start = Instant::now();
+    let mut interval = opts.interval;
+    loop {
+        if let Some(val) = f().await.map_err(WaitError::Failed)? {
+            return Ok(val);
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= opts.timeout {
+            return Err(WaitError::Timeout(opts.timeout));
+        }
+        tokio::time::sleep(interval.min(opts.timeout - elapsed)).await;
+        interval = interval.mul_f64(opts.backoff).min(opts.max_interval);
+    }
+}
@@ -2,14 +2,16 @@ extern crate e2e_tests;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::Read;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use chrono::{SubsecRound, Utc};
 use e2e_tests::anvil::AnvilSetup;
 use e2e_tests::mock_server::MockResponseBodyType;
 use e2e_tests::sharp::SharpClient;
 use e2e_tests::starknet_client::StarknetClient;
-use e2e_tests::utils::{get_mongo_db_client, read_state_update_from_file, vec_u8_to_hex_string};
+use e2e_tests::utils::{
+    get_mongo_db_client, read_state_update_from_file, vec_u8_to_hex_string, wait_for, WaitError, WaitOpts,
+};
 use e2e_tests::{MongoDbServer, Orchestrator};
 use mongodb::bson::doc;
 use orchestrator::core::client::queue::sqs::InnerSQS;
@@ -34,6 +36,9 @@ use serde_json::json;
 use starknet::core::types::{Felt, MaybePendingStateUpdate};
 use uuid::Uuid;
 
+/// How long Anvil has to answer its first RPC request.
+const ANVIL_READY_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// Expected DB state struct
 #[derive(PartialEq, Debug)]
 struct ExpectedDBState {
@@ -68,7 +73,9 @@ impl Setup {
         let sharp_client = SharpClient::new();
         println!("✅ Sharp client setup completed");
 
-        let anvil_setup = AnvilSetup::new();
+        let mut anvil_setup = AnvilSetup::new();
+        let chain_id = anvil_setup.wait_till_ready(ANVIL_READY_TIMEOUT).await.expect("Anvil is not ready");
+        println!("✅ Anvil is ready on chain {}", chain_id);
         let (starknet_core_contract_address, verifier_contract_address) = anvil_setup.deploy_contracts().await;
         println!("✅ Anvil setup completed");
 
@@ -243,21 +250,25 @@ async fn wait_for_db_state(
     mongo_db_server: &MongoDbServer,
     expected_db_state: ExpectedDBState,
 ) -> Result<(), String> {
-    let start = Instant::now();
-
-    while start.elapsed() < timeout {
-        let db_state =
-            get_database_state(mongo_db_server, l2_block_for_testing.clone(), expected_db_state.job_type.clone())
-                .await
-                .unwrap();
+    let expected = &expected_db_state;
+    let res = wait_for(
+        || {
+            let l2_block_for_testing = l2_block_for_testing.clone();
+            async move {
+                let db_state =
+                    get_database_state(mongo_db_server, l2_block_for_testing, expected.job_type.clone()).await?;
+                color_eyre::Result::Ok(db_state.filter(|db_state| db_state == expected).map(|_| ()))
+            }
+        },
+        WaitOpts::new(timeout, Duration::from_millis(100)),
+    )
+    .await;
 
-        if db_state.is_some() && db_state.unwrap() == expected_db_state {
-            return Ok(());
-        }
-        tokio::time::sleep(Duration::from_millis(100)).await;
+    match res {
+        Ok(()) => Ok(()),
+        Err(WaitError::Timeout(_)) => Err(format!("Timed out waiting for expected state: {:?}", expected_db_state)),
+        Err(err @ WaitError::Failed(_)) => Err(format!("Waiting for expected state {:?}: {}", expected_db_state, err)),
     }
-
-    Err(format!("Timed out waiting for expected state: {:?}", expected_db_state))
 }
 
 /// Fetch the job from database
@@ -316,9 +327,10 @@ pub async fn put_job_data_in_db_snos(mongo_db: &MongoDbServer, l2_block_number:
         updated_at: Utc::now().round_subsecs(0),
     };
 
+    mongo_db.drop_database("orchestrator").await.expect("Failed to reset the orchestrator database");
     let mongo_db_client = get_mongo_db_client(mongo_db).await;
-    mongo_db_client.database("orchestrator").drop(None).await.unwrap();
     mongo_db_client.database("orchestrator").collection("jobs").insert_one(job_item.clone(), None).await.unwrap();
+    assert_eq!(mongo_db.count_documents("orchestrator", "jobs").await.expect("Failed to count the jobs"), 1);
 
     job_item.id
 }
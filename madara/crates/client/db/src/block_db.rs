@@ -5,7 +5,7 @@ use crate::events_bloom_filter::{EventBloomReader, EventBloomSearcher};
 use crate::MadaraStorageError;
 use crate::{Column, DatabaseExt, MadaraBackend, SyncStatus, WriteBatchWithTransaction};
 use anyhow::Context;
-use mp_block::event_with_info::{drain_block_events, event_match_filter, EventWithInfo};
+use mp_block::event_with_info::{drain_block_events, EventFilter, EventWithInfo};
 use mp_block::header::{GasPrices, PendingHeader};
 use mp_block::{
     BlockId, MadaraBlock, MadaraBlockInfo, MadaraBlockInner, MadaraMaybePendingBlock, MadaraMaybePendingBlockInfo,
@@ -475,11 +475,10 @@ impl MadaraBackend {
         start_block: u64,
         start_event_index: usize,
         end_block: u64,
-        from_address: Option<&Felt>,
-        keys_pattern: Option<&[Vec<Felt>]>,
+        filter: &EventFilter,
         max_events: usize,
     ) -> Result<Vec<EventWithInfo>> {
-        let key_filter = EventBloomSearcher::new(from_address, keys_pattern);
+        let key_filter = EventBloomSearcher::new(filter.from_address.as_ref(), filter.keys.as_deref());
 
         let mut events_infos = Vec::new();
 
@@ -522,7 +521,7 @@ impl MadaraBackend {
             let mut iter = drain_block_events(block)
                 .enumerate()
                 .skip(skip_events)
-                .filter(|(_, event)| event_match_filter(&event.event, from_address, keys_pattern));
+                .filter(|(_, event)| filter.matches(&event.event));
 
             // Take exactly enough events to fill the requested chunk size.
             events_infos.extend(iter.by_ref().take(max_events - events_infos.len()).map(|(_, event)| event));
@@ -51,7 +51,7 @@ use rocksdb::backup::{BackupEngine, BackupEngineOptions};
 use rocksdb::{
     BoundColumnFamily, ColumnFamilyDescriptor, DBWithThreadMode, Env, FlushOptions, MultiThreaded, WriteOptions,
 };
-use rocksdb_options::rocksdb_global_options;
+use rocksdb_options::{rocksdb_block_cache, rocksdb_global_options};
 use snapshots::Snapshots;
 use starknet_types_core::hash::{Pedersen, Poseidon, StarkHash};
 use std::path::{Path, PathBuf};
@@ -98,11 +98,14 @@ const DB_UPDATES_BATCH_SIZE: usize = 1024;
 
 fn open_rocksdb(path: &Path, config: &RocksDBConfig) -> anyhow::Result<Arc<DB>> {
     let opts = rocksdb_global_options(config)?;
+    let block_cache = rocksdb_block_cache(config);
     tracing::debug!("opening db at {:?}", path.display());
     let db = DB::open_cf_descriptors(
         &opts,
         path,
-        Column::ALL.iter().map(|col| ColumnFamilyDescriptor::new(col.rocksdb_name(), col.rocksdb_options(config))),
+        Column::ALL.iter().map(|col| {
+            ColumnFamilyDescriptor::new(col.rocksdb_name(), col.rocksdb_options(config, block_cache.as_ref()))
+        }),
     )?;
 
     Ok(Arc::new(db))
@@ -644,6 +647,17 @@ impl MadaraBackend {
         Ok(())
     }
 
+    /// Flushes the database and creates a consistent copy of it in `path`, which must not exist yet. SST files are
+    /// hard-linked when `path` is on the same filesystem, so this is cheap and unaffected by background compactions.
+    pub fn checkpoint(&self, path: &Path) -> anyhow::Result<()> {
+        self.flush()?;
+        rocksdb::checkpoint::Checkpoint::new(&self.db)
+            .context("Creating checkpoint object")?
+            .create_checkpoint(path)
+            .with_context(|| format!("Creating database checkpoint at {}", path.display()))?;
+        Ok(())
+    }
+
     #[tracing::instrument(skip(self))]
     pub async fn backup(&self) -> anyhow::Result<()> {
         let (callback_sender, callback_recv) = oneshot::channel();
@@ -3,7 +3,7 @@
 
 use crate::{contract_db, Column};
 use anyhow::{Context, Result};
-use rocksdb::{DBCompressionType, Env, Options, SliceTransform};
+use rocksdb::{BlockBasedOptions, Cache, DBCompressionType, Env, Options, SliceTransform};
 
 const KiB: usize = 1024;
 const MiB: usize = 1024 * KiB;
@@ -27,6 +27,15 @@ pub struct RocksDBConfig {
     pub memtable_other_budget_mib: usize,
     /// Ratio of the buffer size dedicated to bloom filters for a column
     pub memtable_prefix_bloom_filter_ratio: f64,
+    /// Size of the LRU block cache shared between all columns, in MiB. When `None`, every column uses the rocksdb
+    /// default block cache.
+    pub block_cache_size_mib: Option<usize>,
+    /// Total memtable size across all columns, in MiB. Once reached, rocksdb will flush memtables to disk. When
+    /// `None`, only the per-column memtable budgets apply.
+    pub write_buffer_size_mib: Option<usize>,
+    /// Maximum number of concurrent background jobs (flushes and compactions). When `None`, this defaults to the
+    /// number of available cores.
+    pub max_background_jobs: Option<usize>,
 }
 
 impl Default for RocksDBConfig {
@@ -40,6 +49,9 @@ impl Default for RocksDBConfig {
             memtable_contracts_budget_mib: 128 * MiB,
             memtable_other_budget_mib: 128 * MiB,
             memtable_prefix_bloom_filter_ratio: 0.0,
+            block_cache_size_mib: None,
+            write_buffer_size_mib: None,
+            max_background_jobs: None,
         }
     }
 }
@@ -50,7 +62,7 @@ pub fn rocksdb_global_options(config: &RocksDBConfig) -> Result<Options> {
     options.create_missing_column_families(true);
     let cores = std::thread::available_parallelism().map(|e| e.get() as i32).unwrap_or(1);
     options.increase_parallelism(cores);
-    options.set_max_background_jobs(cores);
+    options.set_max_background_jobs(config.max_background_jobs.map(|jobs| jobs as i32).unwrap_or(cores));
 
     options.set_atomic_flush(true);
     options.set_max_subcompactions(cores as _);
@@ -66,6 +78,10 @@ pub fn rocksdb_global_options(config: &RocksDBConfig) -> Result<Options> {
     }
     options.set_stats_dump_period_sec(config.statistics_period_sec);
 
+    if let Some(write_buffer_size_mib) = config.write_buffer_size_mib {
+        options.set_db_write_buffer_size(write_buffer_size_mib * MiB);
+    }
+
     let mut env = Env::new().context("Creating rocksdb env")?;
     env.set_low_priority_background_threads(cores); // compaction
 
@@ -74,10 +90,16 @@ pub fn rocksdb_global_options(config: &RocksDBConfig) -> Result<Options> {
     Ok(options)
 }
 
+/// Block cache shared between all columns. This needs to be created once and given to every column, otherwise
+/// each column would get its own cache of the configured size.
+pub(crate) fn rocksdb_block_cache(config: &RocksDBConfig) -> Option<Cache> {
+    config.block_cache_size_mib.map(|size_mib| Cache::new_lru_cache(size_mib * MiB))
+}
+
 impl Column {
     /// Per column rocksdb options, like memory budget, compaction profiles, block sizes
     /// etc.
-    pub(crate) fn rocksdb_options(&self, config: &RocksDBConfig) -> Options {
+    pub(crate) fn rocksdb_options(&self, config: &RocksDBConfig, block_cache: Option<&Cache>) -> Options {
         // See column-specific options here:
         // https://github.com/facebook/rocksdb/blob/c237022831aa129aa707bc28e0702a1617ef23b5/include/rocksdb/advanced_options.h#L325
         // https://github.com/facebook/rocksdb/blob/c237022831aa129aa707bc28e0702a1617ef23b5/include/rocksdb/advanced_options.h#L148
@@ -111,6 +133,12 @@ impl Column {
                 options.optimize_universal_style_compaction(config.memtable_other_budget_mib);
             }
         }
+
+        if let Some(block_cache) = block_cache {
+            let mut block_based_options = BlockBasedOptions::default();
+            block_based_options.set_block_cache(block_cache);
+            options.set_block_based_table_factory(&block_based_options);
+        }
         options
     }
 }
@@ -1,5 +1,5 @@
 use super::helpers::internal_error_response;
-use crate::helpers::{create_json_response, not_found_response};
+use crate::helpers::{create_json_response, create_string_response, not_found_response};
 use hyper::Response;
 use mc_db::MadaraStorageError;
 use mc_rpc::StarknetRpcApiError;
@@ -18,6 +18,8 @@ pub enum GatewayError {
     StarknetError(#[from] StarknetError),
     #[error("Internal server error: {0}")]
     InternalServerError(String),
+    #[error("Request body is larger than the limit of {0} bytes")]
+    PayloadTooLarge(usize),
 }
 
 impl From<MadaraStorageError> for GatewayError {
@@ -36,6 +38,9 @@ impl From<GatewayError> for Response<String> {
                 internal_error_response()
             }
             GatewayError::Unsupported => not_found_response(),
+            GatewayError::PayloadTooLarge(_) => {
+                create_string_response(hyper::StatusCode::PAYLOAD_TOO_LARGE, e.to_string())
+            }
         }
     }
 }
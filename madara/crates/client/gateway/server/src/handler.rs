@@ -2,13 +2,12 @@ use super::{
     error::{GatewayError, OptionExt, ResultExt},
     helpers::{
         block_id_from_params, create_json_response, create_response_with_json_body, create_string_response,
-        get_params_from_request, include_block_params,
+        get_params_from_request, include_block_params, read_body,
     },
 };
 use crate::helpers::not_found_response;
 use bincode::Options;
 use bytes::Buf;
-use http_body_util::BodyExt;
 use hyper::{body::Incoming, Request, Response, StatusCode};
 use mc_db::MadaraBackend;
 use mc_rpc::{
@@ -335,9 +334,10 @@ pub async fn handle_get_public_key(backend: Arc<MadaraBackend>) -> Result<Respon
 pub async fn handle_add_validated_transaction(
     req: Request<Incoming>,
     submit_validated: Option<Arc<dyn SubmitValidatedTransaction>>,
+    max_request_body_size: usize,
 ) -> Result<Response<String>, GatewayError> {
     let Some(submit_validated) = submit_validated else { return Ok(not_found_response()) };
-    let whole_body = req.collect().await.or_internal_server_error("Failed to read request body")?.aggregate();
+    let whole_body = read_body(req, max_request_body_size).await?;
 
     let transaction: ValidatedMempoolTx = bincode::options()
         .with_little_endian()
@@ -355,8 +355,9 @@ pub async fn handle_add_validated_transaction(
 pub async fn handle_add_transaction(
     req: Request<Incoming>,
     add_transaction_provider: Arc<dyn SubmitTransaction>,
+    max_request_body_size: usize,
 ) -> Result<Response<String>, GatewayError> {
-    let whole_body = req.collect().await.or_internal_server_error("Failed to read request body")?.aggregate();
+    let whole_body = read_body(req, max_request_body_size).await?;
 
     let transaction = serde_json::from_reader::<_, UserTransaction>(whole_body.reader())
         .map_err(|e| GatewayError::StarknetError(StarknetError::malformed_request(e)))?;
@@ -1,11 +1,15 @@
 use std::collections::HashMap;
 
+use bytes::Buf;
+use http_body_util::{BodyExt, LengthLimitError, Limited};
 use hyper::{body::Incoming, header, Request, Response, StatusCode};
 use mp_block::{BlockId, BlockTag};
 use mp_gateway::error::{StarknetError, StarknetErrorCode};
 use serde::Serialize;
 use starknet_types_core::felt::Felt;
 
+use crate::error::GatewayError;
+
 pub(crate) fn service_unavailable_response(service_name: &str) -> Response<String> {
     Response::builder()
         .status(StatusCode::SERVICE_UNAVAILABLE)
@@ -79,6 +83,18 @@ pub(crate) fn create_response_with_json_body(status: StatusCode, body: String) -
     }
 }
 
+/// Reads the whole body of the request, without ever buffering more than `limit` bytes.
+pub(crate) async fn read_body(req: Request<Incoming>, limit: usize) -> Result<impl Buf, GatewayError> {
+    match Limited::new(req.into_body(), limit).collect().await {
+        Ok(body) => Ok(body.aggregate()),
+        Err(err) if err.is::<LengthLimitError>() => Err(GatewayError::PayloadTooLarge(limit)),
+        Err(err) => {
+            tracing::error!(target: "gateway_errors", "Failed to read request body: {err:#}");
+            Err(GatewayError::InternalServerError(err.to_string()))
+        }
+    }
+}
+
 pub(crate) fn get_params_from_request(req: &Request<Incoming>) -> HashMap<String, String> {
     let query = req.uri().query().unwrap_or("");
     let params = query.split('&');
@@ -25,7 +25,7 @@ pub(crate) async fn main_router(
     match (path.as_ref(), config.feeder_gateway_enable, config.gateway_enable) {
         ("health", _, _) => Ok(Response::new("OK".to_string())),
         (path, true, _) if path.starts_with("gateway/") => {
-            Ok(gateway_router(req, path, add_transaction_provider).await?)
+            Ok(gateway_router(req, path, add_transaction_provider, config.max_request_body_size).await?)
         }
         (path, true, _) if path.starts_with("feeder_gateway/") => {
             Ok(feeder_gateway_router(req, path, backend, add_transaction_provider, ctx).await?)
@@ -34,7 +34,9 @@ pub(crate) async fn main_router(
             if path.starts_with("madara/trusted_add_validated_transaction")
                 && config.enable_trusted_add_validated_transaction =>
         {
-            Ok(handle_add_validated_transaction(req, submit_validated).await.unwrap_or_else(Into::into))
+            Ok(handle_add_validated_transaction(req, submit_validated, config.max_request_body_size)
+                .await
+                .unwrap_or_else(Into::into))
         }
         (path, false, _) if path.starts_with("feeder_gateway/") => Ok(service_unavailable_response("Feeder Gateway")),
         (path, _, false) if path.starts_with("gateway/") => Ok(service_unavailable_response("Feeder")),
@@ -90,10 +92,13 @@ async fn gateway_router(
     req: Request<Incoming>,
     path: &str,
     add_transaction_provider: Arc<dyn SubmitTransaction>,
+    max_request_body_size: usize,
 ) -> Result<Response<String>, Infallible> {
     match (req.method(), path) {
         (&Method::POST, "gateway/add_transaction") => {
-            Ok(handle_add_transaction(req, add_transaction_provider).await.unwrap_or_else(Into::into))
+            Ok(handle_add_transaction(req, add_transaction_provider, max_request_body_size)
+                .await
+                .unwrap_or_else(Into::into))
         }
         _ => {
             tracing::debug!(target: "feeder_gateway", "Gateway received invalid request: {path}");
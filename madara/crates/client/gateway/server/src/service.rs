@@ -18,6 +18,8 @@ pub struct GatewayServerConfig {
     pub gateway_external: bool,
     pub gateway_port: u16,
     pub enable_trusted_add_validated_transaction: bool,
+    /// Requests with a larger body are rejected with a `413 Payload Too Large`.
+    pub max_request_body_size: usize,
 }
 impl Default for GatewayServerConfig {
     fn default() -> Self {
@@ -27,6 +29,7 @@ impl Default for GatewayServerConfig {
             gateway_external: false,
             gateway_port: 8080,
             enable_trusted_add_validated_transaction: false,
+            max_request_body_size: 15 * 1024 * 1024,
         }
     }
 }
@@ -19,7 +19,8 @@ use mp_chain_config::ChainConfig;
 use mp_convert::ToFelt;
 use mp_utils::service::ServiceContext;
 use starknet_types_core::felt::Felt;
-use std::sync::Arc;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
 use utils::ResultExt;
 
 pub use errors::{StarknetRpcApiError, StarknetRpcResult};
@@ -41,12 +42,16 @@ impl Default for StorageProofConfig {
     }
 }
 
+/// Compiled classes by class hash.
+type CasmCache = Mutex<lru::LruCache<Felt, serde_json::Value>>;
+
 /// A Starknet RPC server for Madara
 #[derive(Clone)]
 pub struct Starknet {
     backend: Arc<MadaraBackend>,
     pub(crate) add_transaction_provider: Arc<dyn SubmitTransaction>,
     storage_proof_config: StorageProofConfig,
+    casm_cache: Option<Arc<CasmCache>>,
     pub ctx: ServiceContext,
 }
 
@@ -57,7 +62,14 @@ impl Starknet {
         storage_proof_config: StorageProofConfig,
         ctx: ServiceContext,
     ) -> Self {
-        Self { backend, add_transaction_provider, storage_proof_config, ctx }
+        Self { backend, add_transaction_provider, storage_proof_config, casm_cache: None, ctx }
+    }
+
+    /// Keeps the last `size` classes returned by `starknet_getCompiledCasm` in memory, a size of 0 disables the
+    /// cache. The compiled class of a class hash never changes, so cached entries never need to be invalidated.
+    pub fn with_casm_cache_size(self, size: usize) -> Self {
+        let casm_cache = NonZeroUsize::new(size).map(|size| Arc::new(Mutex::new(lru::LruCache::new(size))));
+        Self { casm_cache, ..self }
     }
 
     pub fn clone_backend(&self) -> Arc<MadaraBackend> {
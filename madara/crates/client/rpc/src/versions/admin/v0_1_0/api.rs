@@ -3,6 +3,7 @@ use m_proc_macros::versioned_rpc;
 use mp_rpc::{admin::BroadcastedDeclareTxnV0, ClassAndTxnHash};
 use mp_utils::service::{MadaraServiceId, MadaraServiceStatus};
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "snake_case")]
@@ -21,6 +22,18 @@ pub trait MadaraWriteRpcApi {
         &self,
         declare_v0_transaction: BroadcastedDeclareTxnV0,
     ) -> RpcResult<ClassAndTxnHash>;
+
+    /// Creates a consistent snapshot of the database, which can be opened as the `db` directory of another node.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Directory to create the snapshot in, on the machine running the node. It must not exist yet.
+    ///
+    /// # Returns
+    ///
+    /// * Number of the latest block in the snapshot, if any.
+    #[method(name = "checkpoint")]
+    async fn checkpoint(&self, path: PathBuf) -> RpcResult<Option<u64>>;
 }
 
 #[versioned_rpc("V0_1_0", "madara")]
@@ -1,6 +1,7 @@
-use crate::{versions::admin::v0_1_0::MadaraWriteRpcApiV0_1_0Server, Starknet, StarknetRpcApiError};
+use crate::{utils::ResultExt, versions::admin::v0_1_0::MadaraWriteRpcApiV0_1_0Server, Starknet, StarknetRpcApiError};
 use jsonrpsee::core::{async_trait, RpcResult};
 use mp_rpc::{admin::BroadcastedDeclareTxnV0, ClassAndTxnHash};
+use std::path::PathBuf;
 
 #[async_trait]
 impl MadaraWriteRpcApiV0_1_0Server for Starknet {
@@ -23,4 +24,13 @@ impl MadaraWriteRpcApiV0_1_0Server for Starknet {
             .await
             .map_err(StarknetRpcApiError::from)?)
     }
+
+    #[tracing::instrument(skip(self), fields(module = "Admin"))]
+    async fn checkpoint(&self, path: PathBuf) -> RpcResult<Option<u64>> {
+        // The head is read first: blocks imported in the meantime are only ever added on top of it.
+        let block_n = self.backend.get_latest_block_n().or_internal_server_error("Getting the latest block number")?;
+        self.backend.checkpoint(&path).or_internal_server_error("Creating a database checkpoint")?;
+        tracing::info!("💾 Created database checkpoint at block {block_n:?} in {}", path.display());
+        Ok(block_n)
+    }
 }
@@ -1,2 +1,51 @@
 pub mod admin;
 pub mod user;
+
+use mp_chain_config::{RpcVersion, RpcVersionError};
+
+/// Error returned when a request path targets an rpc version which is not served by this endpoint, such as
+/// `/rpc/v9_9_9`. The error lists every supported version, so that clients know what they can fall back to.
+pub fn unsupported_version_error(path: &str, supported: &[RpcVersion]) -> jsonrpsee::types::ErrorObjectOwned {
+    let supported = supported.iter().map(RpcVersion::module).collect::<Vec<_>>();
+    jsonrpsee::types::ErrorObject::owned(
+        jsonrpsee::types::error::METHOD_NOT_FOUND_CODE,
+        format!("Unsupported RPC version in request path {path:?}, supported versions are: {}", supported.join(", ")),
+        Some(serde_json::json!({ "supported_versions": supported })),
+    )
+}
+
+/// Error returned when the version segment of a request path cannot be parsed, such as `/rpc/vx_y_z`.
+pub fn invalid_version_error(path: &str, err: &RpcVersionError) -> jsonrpsee::types::ErrorObjectOwned {
+    jsonrpsee::types::ErrorObject::owned(
+        jsonrpsee::types::error::INVALID_REQUEST_CODE,
+        format!("Invalid RPC version in request path {path:?}: {err}"),
+        None::<()>,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mp_chain_config::SUPPORTED_RPC_VERSIONS_USER;
+
+    #[test]
+    fn test_unsupported_version_error_lists_supported_versions() {
+        let err = unsupported_version_error("/rpc/v9_9_9", SUPPORTED_RPC_VERSIONS_USER);
+
+        assert_eq!(err.code(), jsonrpsee::types::error::METHOD_NOT_FOUND_CODE);
+        assert!(err.message().contains("v0_7_1"), "message: {}", err.message());
+        assert!(err.message().contains("v0_8_0"), "message: {}", err.message());
+
+        let data: serde_json::Value = serde_json::from_str(err.data().expect("Error should have data").get()).unwrap();
+        assert_eq!(data, serde_json::json!({ "supported_versions": ["v0_7_1", "v0_8_0"] }));
+    }
+
+    #[test]
+    fn test_invalid_version_error() {
+        let err = invalid_version_error("/rpc/vx_y_z", &RpcVersionError::InvalidVersion);
+
+        assert_eq!(err.code(), jsonrpsee::types::error::INVALID_REQUEST_CODE);
+        assert!(err.message().contains("/rpc/vx_y_z"), "message: {}", err.message());
+        assert!(!err.message().contains("Unsupported"), "message: {}", err.message());
+    }
+}
@@ -1,3 +1,4 @@
+use mp_block::event_with_info::EventFilter;
 use mp_block::{BlockId, BlockTag, EventWithInfo};
 use mp_rpc::{EmittedEvent, Event, EventContent, EventFilterWithPageRequest, EventsChunk};
 
@@ -28,11 +29,10 @@ use crate::Starknet;
 /// errors, such as `PAGE_SIZE_TOO_BIG`, `INVALID_CONTINUATION_TOKEN`, `BLOCK_NOT_FOUND`, or
 /// `TOO_MANY_KEYS_IN_FILTER`, returns a `StarknetRpcApiError` indicating the specific issue.
 pub async fn get_events(starknet: &Starknet, filter: EventFilterWithPageRequest) -> StarknetRpcResult<EventsChunk> {
-    let from_address = filter.address;
-    let keys = filter.keys;
+    let event_filter = EventFilter::new(filter.address, filter.keys);
     let chunk_size = filter.chunk_size as usize;
 
-    if keys.as_ref().map(|k| k.iter().map(|pattern| pattern.len()).sum()).unwrap_or(0) > MAX_EVENTS_KEYS {
+    if event_filter.keys_len() > MAX_EVENTS_KEYS {
         return Err(StarknetRpcApiError::TooManyKeysInFilter);
     }
     if chunk_size > MAX_EVENTS_CHUNK_SIZE {
@@ -52,19 +52,25 @@ pub async fn get_events(starknet: &Starknet, filter: EventFilterWithPageRequest)
         return Ok(EventsChunk { events: vec![], continuation_token: None });
     }
 
+    // A token pointing outside of the requested range cannot have been issued for this filter
+    if !(from_block..=to_block).contains(&continuation_token.block_n) {
+        return Err(StarknetRpcApiError::InvalidContinuationToken);
+    }
+
     let from_block = continuation_token.block_n;
     let from_event_n = continuation_token.event_n as usize;
 
     let mut events_infos = starknet
         .backend
-        .get_filtered_events(from_block, from_event_n, to_block, from_address.as_ref(), keys.as_deref(), chunk_size + 1)
+        .get_filtered_events(from_block, from_event_n, to_block, &event_filter, chunk_size + 1)
         .or_internal_server_error("Error getting filtered events")?;
 
+    // The extra event we fetched is not returned: the next chunk resumes exactly at its position
     let mut continuation_token = None;
     if events_infos.len() > chunk_size {
         continuation_token = events_infos.pop().and_then(|event_info| match event_info {
             EventWithInfo { block_number: Some(block_n), event_index_in_block, .. } => {
-                Some(ContinuationToken { block_n, event_n: (event_index_in_block + 1) as u64 })
+                Some(ContinuationToken { block_n, event_n: event_index_in_block as u64 })
             }
             _ => None,
         });
@@ -105,3 +111,140 @@ fn block_range(
     };
     Ok((from_block_n, to_block_n, latest_block_n))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::rpc_test_setup;
+    use mc_db::MadaraBackend;
+    use mp_receipt::{EventWithTransactionHash, InvokeTransactionReceipt, TransactionReceipt};
+    use rstest::rstest;
+    use starknet_types_core::felt::Felt;
+    use std::sync::Arc;
+
+    /// Stores `n_blocks` blocks with `events_per_block` events each, and returns all the events in order.
+    fn store_blocks_with_events(backend: &MadaraBackend, n_blocks: u64, events_per_block: u64) -> Vec<EmittedEvent> {
+        let mut expected = vec![];
+        for block_n in 0..n_blocks {
+            let tx_hash = Felt::from(block_n);
+            let events = (0..events_per_block)
+                .map(|event_n| mp_receipt::Event {
+                    from_address: Felt::from((block_n << 16) | event_n),
+                    keys: vec![Felt::from(event_n)],
+                    data: vec![],
+                })
+                .collect::<Vec<_>>();
+            let receipts = vec![TransactionReceipt::Invoke(InvokeTransactionReceipt {
+                transaction_hash: tx_hash,
+                events: events.clone(),
+                ..Default::default()
+            })];
+
+            backend
+                .store_block(
+                    mp_block::MadaraMaybePendingBlock {
+                        info: mp_block::MadaraMaybePendingBlockInfo::NotPending(mp_block::MadaraBlockInfo {
+                            header: mp_block::Header { block_number: block_n, ..Default::default() },
+                            block_hash: Felt::from(block_n),
+                            tx_hashes: vec![tx_hash],
+                        }),
+                        inner: mp_block::MadaraBlockInner { transactions: vec![], receipts },
+                    },
+                    mp_state_update::StateDiff::default(),
+                    vec![],
+                )
+                .expect("Storing block");
+            // Also writes the events bloom filter of the block
+            backend
+                .store_events(
+                    block_n,
+                    events
+                        .iter()
+                        .map(|event| EventWithTransactionHash { transaction_hash: tx_hash, event: event.clone() })
+                        .collect(),
+                )
+                .expect("Storing events");
+
+            expected.extend(events.into_iter().map(|event| EmittedEvent {
+                event: Event {
+                    from_address: event.from_address,
+                    event_content: EventContent { keys: event.keys, data: event.data },
+                },
+                block_hash: Some(Felt::from(block_n)),
+                block_number: Some(block_n),
+                transaction_hash: tx_hash,
+            }));
+        }
+        expected
+    }
+
+    fn page_request(chunk_size: u64, continuation_token: Option<String>) -> EventFilterWithPageRequest {
+        EventFilterWithPageRequest {
+            address: None,
+            from_block: Some(BlockId::Number(0)),
+            keys: None,
+            to_block: Some(BlockId::Tag(BlockTag::Latest)),
+            chunk_size,
+            continuation_token,
+        }
+    }
+
+    #[rstest]
+    #[case::within_blocks(2)]
+    #[case::block_sized(3)]
+    #[case::across_blocks(5)]
+    #[tokio::test]
+    async fn get_events_pages_through_blocks(rpc_test_setup: (Arc<MadaraBackend>, Starknet), #[case] chunk_size: u64) {
+        let (backend, starknet) = rpc_test_setup;
+        let expected = store_blocks_with_events(&backend, 4, 3);
+
+        let mut events = vec![];
+        let mut continuation_token = None;
+        loop {
+            let chunk = get_events(&starknet, page_request(chunk_size, continuation_token.clone())).await.unwrap();
+            assert!(chunk.events.len() <= chunk_size as usize);
+
+            // The same token always resumes at the same position
+            let again = get_events(&starknet, page_request(chunk_size, continuation_token)).await.unwrap();
+            assert_eq!(again.events, chunk.events);
+            assert_eq!(again.continuation_token, chunk.continuation_token);
+
+            events.extend(chunk.events);
+            match chunk.continuation_token {
+                Some(token) => continuation_token = Some(token),
+                None => break,
+            }
+        }
+
+        assert_eq!(events, expected);
+    }
+
+    #[rstest]
+    #[case::malformed("not-a-token")]
+    #[case::not_a_number("1-x")]
+    #[case::after_range("10-0")]
+    #[tokio::test]
+    async fn get_events_invalid_continuation_token(
+        rpc_test_setup: (Arc<MadaraBackend>, Starknet),
+        #[case] token: &str,
+    ) {
+        let (backend, starknet) = rpc_test_setup;
+        store_blocks_with_events(&backend, 4, 3);
+
+        let res = get_events(&starknet, page_request(2, Some(token.to_string()))).await;
+        assert_eq!(res, Err(StarknetRpcApiError::InvalidContinuationToken));
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn get_events_continuation_token_before_range(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (backend, starknet) = rpc_test_setup;
+        store_blocks_with_events(&backend, 4, 3);
+
+        let filter = EventFilterWithPageRequest {
+            from_block: Some(BlockId::Number(2)),
+            ..page_request(2, Some("1-0".to_string()))
+        };
+        assert_eq!(get_events(&starknet, filter).await, Err(StarknetRpcApiError::InvalidContinuationToken));
+    }
+}
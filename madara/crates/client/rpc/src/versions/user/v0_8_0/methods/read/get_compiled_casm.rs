@@ -8,6 +8,19 @@ use crate::utils::ResultExt;
 use crate::Starknet;
 
 pub fn get_compiled_casm(starknet: &Starknet, class_hash: Felt) -> StarknetRpcResult<serde_json::Value> {
+    let Some(casm_cache) = &starknet.casm_cache else { return get_compiled_casm_from_db(starknet, class_hash) };
+
+    if let Some(res) = casm_cache.lock().expect("Poisoned lock").get(&class_hash) {
+        return Ok(res.clone());
+    }
+    // The lock is not held while reading the database: concurrent misses on the same class hash all read it, and
+    // insert the same value.
+    let res = get_compiled_casm_from_db(starknet, class_hash)?;
+    casm_cache.lock().expect("Poisoned lock").put(class_hash, res.clone());
+    Ok(res)
+}
+
+fn get_compiled_casm_from_db(starknet: &Starknet, class_hash: Felt) -> StarknetRpcResult<serde_json::Value> {
     let compiled_class_hash = starknet
         .backend
         .get_class_info(&BlockId::Tag(BlockTag::Latest), &class_hash)
@@ -30,3 +43,62 @@ pub fn get_compiled_casm(starknet: &Starknet, class_hash: Felt) -> StarknetRpcRe
 
     Ok(res)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use mc_db::tests::common::finalized_block_one;
+    use mp_state_update::{DeclaredClassItem, StateDiff};
+
+    use super::*;
+    use crate::test_utils::rpc_test_setup;
+
+    #[test]
+    fn get_compiled_casm_cache_hit() {
+        let (backend, starknet) = rpc_test_setup();
+        let starknet = starknet.with_casm_cache_size(8);
+        let class_hash = Felt::from_hex_unchecked("0x9100000001");
+        let compiled_class_hash = Felt::from_hex_unchecked("0x9100000006");
+
+        let class = mp_class::ConvertedClass::Sierra(mp_class::SierraConvertedClass {
+            class_hash,
+            info: mp_class::SierraClassInfo {
+                contract_class: Arc::new(mp_class::FlattenedSierraClass {
+                    sierra_program: vec![],
+                    contract_class_version: "".to_string(),
+                    entry_points_by_type: mp_class::EntryPointsByType {
+                        constructor: vec![],
+                        external: vec![],
+                        l1_handler: vec![],
+                    },
+                    abi: "".to_string(),
+                }),
+                compiled_class_hash,
+            },
+            compiled: Arc::new(mp_class::CompiledSierra(r#"{"prime":"0x1"}"#.to_string())),
+        });
+        let state_diff = StateDiff {
+            declared_classes: vec![DeclaredClassItem { class_hash, compiled_class_hash }],
+            ..Default::default()
+        };
+        backend.store_block(finalized_block_one(), state_diff, vec![class]).unwrap();
+
+        let casm = get_compiled_casm(&starknet, class_hash).unwrap();
+        assert_eq!(casm, serde_json::json!({ "prime": "0x1" }));
+
+        // The second call is answered from the cache, without reading the database.
+        let casm_cache = starknet.casm_cache.as_ref().unwrap();
+        assert_eq!(casm_cache.lock().unwrap().peek(&class_hash), Some(&casm));
+        casm_cache.lock().unwrap().put(class_hash, serde_json::json!("cached"));
+        assert_eq!(get_compiled_casm(&starknet, class_hash).unwrap(), serde_json::json!("cached"));
+
+        // Unknown classes are not cached.
+        let unknown = Felt::from_hex_unchecked("0xdead");
+        assert_matches::assert_matches!(
+            get_compiled_casm(&starknet, unknown),
+            Err(StarknetRpcApiError::ClassHashNotFound { .. })
+        );
+        assert!(!casm_cache.lock().unwrap().contains(&unknown));
+    }
+}
@@ -1,6 +1,6 @@
 use crate::errors::{ErrorExtWs, StarknetWsApiError};
 use mp_block::{
-    event_with_info::{drain_block_events, event_match_filter},
+    event_with_info::{drain_block_events, EventFilter},
     BlockId,
 };
 use mp_rpc::EmittedEvent;
@@ -17,6 +17,7 @@ pub async fn subscribe_events(
 ) -> Result<(), StarknetWsApiError> {
     let sink = subscription_sink.accept().await.or_internal_server_error("Failed to establish websocket connection")?;
 
+    let filter = EventFilter::new(from_address, keys);
     let mut rx = starknet.backend.subscribe_events(from_address);
 
     if let Some(block_id) = block_id {
@@ -41,9 +42,7 @@ pub async fn subscribe_events(
             let block = starknet
                 .get_block(&BlockId::Number(block_number))
                 .or_internal_server_error("Failed to retrieve block")?;
-            for event in drain_block_events(block)
-                .filter(|event| event_match_filter(&event.event, from_address.as_ref(), keys.as_deref()))
-            {
+            for event in drain_block_events(block).filter(|event| filter.matches(&event.event)) {
                 let msg = jsonrpsee::SubscriptionMessage::from_json(&EmittedEvent::from(event))
                     .or_internal_server_error("Failed to create response message")?;
                 sink.send(msg).await.or_internal_server_error("Failed to respond to websocket request")?;
@@ -55,7 +54,7 @@ pub async fn subscribe_events(
         tokio::select! {
             event = rx.recv() => {
                 let event = event.or_internal_server_error("Failed to retrieve event")?;
-                if event_match_filter(&event.event, from_address.as_ref(), keys.as_deref()) {
+                if filter.matches(&event.event) {
                     let msg = jsonrpsee::SubscriptionMessage::from_json(&EmittedEvent::from(event))
                         .or_internal_server_error("Failed to create response message")?;
                     sink.send(msg).await.or_internal_server_error("Failed to respond to websocket request")?;
@@ -1,5 +1,5 @@
 use crate::{
-    import::BlockImporter,
+    import::{BlockImporter, ImportError},
     pipeline::{ApplyOutcome, PipelineController, PipelineSteps},
 };
 use anyhow::Context;
@@ -49,7 +49,10 @@ impl PipelineSteps for ApplyStateSteps {
         let block_range_ = block_range.clone();
         // Importer is in charge of setting the head status.
         self.importer
-            .run_in_rayon_pool_global(move |importer| importer.apply_to_global_trie(block_range_, input))
+            .run_in_rayon_pool_global(move |importer| {
+                let start = block_range_.start;
+                importer.apply_to_global_trie(block_range_, input).map_err(|err| ImportError::new(start, err))
+            })
             .await
             .with_context(|| format!("Applying global trie step for block_range={block_range:?}"))?;
         Ok(ApplyOutcome::Success(()))
@@ -1,5 +1,5 @@
 use crate::{
-    import::BlockImporter,
+    import::{BlockImporter, ImportError},
     pipeline::{ApplyOutcome, PipelineController, PipelineSteps},
     probe::ThrottledRepeatedFuture,
 };
@@ -34,7 +34,6 @@ pub fn block_with_state_update_pipeline(
     )
 }
 
-// TODO: check that the headers follow each other
 pub struct GatewaySyncSteps {
     backend: Arc<MadaraBackend>,
     importer: Arc<BlockImporter>,
@@ -43,7 +42,8 @@ pub struct GatewaySyncSteps {
 }
 impl PipelineSteps for GatewaySyncSteps {
     type InputItem = ();
-    type SequentialStepInput = Vec<StateDiff>;
+    /// Parent hash and state diff of each block.
+    type SequentialStepInput = Vec<(Felt, StateDiff)>;
     type Output = Vec<StateDiff>;
 
     async fn parallel_step(
@@ -65,13 +65,18 @@ impl PipelineSteps for GatewaySyncSteps {
                     anyhow::bail!("Asked for a block_n, got a pending one")
                 };
 
-                let gateway_block: FullBlock = block.into_full_block().context("Parsing gateway block")?;
+                let gateway_block: FullBlock = block
+                    .into_full_block()
+                    .context("Parsing gateway block")
+                    .map_err(|err| ImportError::body_conversion(block_n, err))?;
 
                 let keep_pre_v0_13_2_hashes = self.keep_pre_v0_13_2_hashes;
+                let parent_hash = gateway_block.header.parent_block_hash;
 
                 let state_diff = self
                     .importer
                     .run_in_rayon_pool(move |importer| {
+                        let import_err = |err| ImportError::new(block_n, err);
                         let mut signed_header = BlockHeaderWithSignatures {
                             header: gateway_block.header,
                             block_hash: gateway_block.block_hash,
@@ -81,24 +86,25 @@ impl PipelineSteps for GatewaySyncSteps {
                         // Allow the gateway format, which has legacy commitments.
                         let allow_pre_v0_13_2 = true;
 
-                        let state_diff_commitment = importer.verify_state_diff(
-                            block_n,
-                            &gateway_block.state_diff,
-                            &signed_header.header,
-                            allow_pre_v0_13_2,
-                        )?;
-                        let (transaction_commitment, receipt_commitment) = importer.verify_transactions(
-                            block_n,
-                            &gateway_block.transactions,
-                            &signed_header.header,
-                            allow_pre_v0_13_2,
-                        )?;
-                        let event_commitment = importer.verify_events(
-                            block_n,
-                            &gateway_block.events,
-                            &signed_header.header,
-                            allow_pre_v0_13_2,
-                        )?;
+                        let state_diff_commitment = importer
+                            .verify_state_diff(
+                                block_n,
+                                &gateway_block.state_diff,
+                                &signed_header.header,
+                                allow_pre_v0_13_2,
+                            )
+                            .map_err(import_err)?;
+                        let (transaction_commitment, receipt_commitment) = importer
+                            .verify_transactions(
+                                block_n,
+                                &gateway_block.transactions,
+                                &signed_header.header,
+                                allow_pre_v0_13_2,
+                            )
+                            .map_err(import_err)?;
+                        let event_commitment = importer
+                            .verify_events(block_n, &gateway_block.events, &signed_header.header, allow_pre_v0_13_2)
+                            .map_err(import_err)?;
                         if !keep_pre_v0_13_2_hashes {
                             // Fill in the header with the commitments missing in pre-v0.13.2 headers from the gateway.
                             signed_header.header = Header {
@@ -109,18 +115,18 @@ impl PipelineSteps for GatewaySyncSteps {
                                 ..signed_header.header
                             };
                         }
-                        importer.verify_header(block_n, &signed_header)?;
+                        importer.verify_header(block_n, &signed_header).map_err(import_err)?;
 
-                        importer.save_header(block_n, signed_header)?;
-                        importer.save_state_diff(block_n, gateway_block.state_diff.clone())?;
-                        importer.save_transactions(block_n, gateway_block.transactions)?;
-                        importer.save_events(block_n, gateway_block.events)?;
+                        importer.save_header(block_n, signed_header).map_err(import_err)?;
+                        importer.save_state_diff(block_n, gateway_block.state_diff.clone()).map_err(import_err)?;
+                        importer.save_transactions(block_n, gateway_block.transactions).map_err(import_err)?;
+                        importer.save_events(block_n, gateway_block.events).map_err(import_err)?;
 
-                        anyhow::Ok(gateway_block.state_diff)
+                        Ok::<_, ImportError>(gateway_block.state_diff)
                     })
                     .await
                     .with_context(|| format!("Verifying block for block_n={block_n:?}"))?;
-                out.push(state_diff);
+                out.push((parent_hash, state_diff));
             }
             Ok(out)
         })
@@ -132,6 +138,18 @@ impl PipelineSteps for GatewaySyncSteps {
         input: Self::SequentialStepInput,
     ) -> anyhow::Result<ApplyOutcome<Self::Output>> {
         tracing::debug!("Gateway sync sequential step: {block_range:?}");
+        let (parent_hashes, state_diffs): (Vec<_>, Vec<_>) = input.into_iter().unzip();
+        // Blocks are applied in order, so the block below each one of them is already stored.
+        let block_range_ = block_range.clone();
+        self.importer
+            .run_in_rayon_pool_global(move |importer| {
+                for (block_n, parent_hash) in block_range_.zip(parent_hashes) {
+                    importer.verify_parent_hash(block_n, parent_hash).map_err(|err| ImportError::new(block_n, err))?;
+                }
+                Ok::<_, ImportError>(())
+            })
+            .await
+            .with_context(|| format!("Checking parent hashes for block_range={block_range:?}"))?;
         if let Some(block_n) = block_range.last() {
             self.backend.clear_pending_block().context("Clearing pending block")?;
             self.backend.head_status().headers.set_current(Some(block_n));
@@ -140,7 +158,7 @@ impl PipelineSteps for GatewaySyncSteps {
             self.backend.head_status().events.set_current(Some(block_n));
             self.backend.save_head_status_to_db()?;
         }
-        Ok(ApplyOutcome::Success(input))
+        Ok(ApplyOutcome::Success(state_diffs))
     }
 }
 
@@ -61,7 +61,7 @@ pub fn forward_sync(
     config: ForwardSyncConfig,
 ) -> GatewaySync {
     let probe = Arc::new(GatewayLatestProbe::new(client.clone()));
-    let probe = ThrottledRepeatedFuture::new(move |val| probe.clone().probe(val), Duration::from_secs(1));
+    let probe = ThrottledRepeatedFuture::new(move |_| probe.clone().probe(), Duration::from_secs(1));
     let get_pending_block = gateway_pending_block_sync(client.clone(), importer.clone(), backend.clone());
     SyncController::new(
         backend.clone(),
@@ -228,10 +228,9 @@ impl GatewayLatestProbe {
     pub fn new(client: Arc<GatewayProvider>) -> Self {
         Self { client }
     }
-    async fn probe(
-        self: Arc<Self>,
-        _highest_known_block: Option<ProviderBlockHeader>,
-    ) -> anyhow::Result<Option<ProviderBlockHeader>> {
+    /// Gateway errors are returned as is: the sync controller keeps the previous header until the gateway answers
+    /// again, and fails if there is none.
+    async fn probe(self: Arc<Self>) -> anyhow::Result<Option<ProviderBlockHeader>> {
         let header = self
             .client
             .get_header(BlockId::Tag(BlockTag::Latest))
@@ -16,7 +16,17 @@ use mp_utils::rayon::{global_spawn_rayon_task, RayonPool};
 use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use starknet_api::core::ChainId;
 use starknet_core::types::Felt;
-use std::{borrow::Cow, collections::HashMap, ops::Range, sync::Arc};
+use std::{
+    borrow::Cow,
+    cmp,
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 #[derive(Clone, Debug, Eq, PartialEq, Default)]
 pub struct BlockValidationConfig {
@@ -30,6 +40,24 @@ pub struct BlockValidationConfig {
 
     /// Save pre-v0.13.2 commitments.
     pub pre_v0_13_2_commitments: bool,
+
+    /// Check the global state root after every block when applying a batch of state diffs to the global trie,
+    /// instead of only checking it for the last block of the batch. This catches corrupt state diffs at the block
+    /// they were introduced, at the cost of one header lookup per block.
+    pub verify_each_global_state_root: bool,
+
+    /// Do not compute the global trie, and trust the state roots of the block headers instead. The global trie head
+    /// status is left behind, and the skipped blocks are applied from the state diffs saved in the database, with
+    /// the usual state root verification, once the node imports blocks without this option.
+    ///
+    /// This is only safe when syncing from a trusted source: until the trie has caught up, nothing checks that the
+    /// state diffs actually lead to the state roots in the headers, and storage proofs are unavailable.
+    pub trust_global_trie: bool,
+
+    /// Append the time spent applying each block to the global trie to this CSV file, as
+    /// `block_n,micros,storage_updates` rows. Blocks are then applied one by one instead of in batches, so this is
+    /// only meant for profiling.
+    pub global_trie_timings: Option<PathBuf>,
 }
 
 impl BlockValidationConfig {
@@ -42,6 +70,15 @@ impl BlockValidationConfig {
     pub fn pre_v0_13_2_commitments(self, pre_v0_13_2_commitments: bool) -> Self {
         Self { pre_v0_13_2_commitments, ..self }
     }
+    pub fn verify_each_global_state_root(self, verify_each_global_state_root: bool) -> Self {
+        Self { verify_each_global_state_root, ..self }
+    }
+    pub fn trust_global_trie(self, trust_global_trie: bool) -> Self {
+        Self { trust_global_trie, ..self }
+    }
+    pub fn global_trie_timings(self, global_trie_timings: Option<PathBuf>) -> Self {
+        Self { global_trie_timings, ..self }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -83,9 +120,11 @@ pub enum BlockImportError {
     BlockNumber { got: u64, expected: u64 },
     #[error("Block hash mismatch: expected {expected:#x}, got {got:#x}")]
     BlockHash { got: Felt, expected: Felt },
+    #[error("Parent block hash mismatch: expected {expected:#x}, got {got:#x}")]
+    ParentHash { got: Felt, expected: Felt },
 
-    #[error("Global state root mismatch: expected {expected:#x}, got {got:#x}")]
-    GlobalStateRoot { got: Felt, expected: Felt },
+    #[error("Global state root mismatch for block #{block_n}: expected {expected:#x}, got {got:#x}")]
+    GlobalStateRoot { block_n: u64, got: Felt, expected: Felt },
     /// Internal error, see [`BlockImportError::is_internal`].
     #[error("Internal database error while {context}: {error:#}")]
     InternalDb { context: Cow<'static, str>, error: MadaraStorageError },
@@ -98,6 +137,98 @@ impl BlockImportError {
     pub fn is_internal(&self) -> bool {
         matches!(self, BlockImportError::InternalDb { .. } | BlockImportError::Internal(_))
     }
+
+    pub fn kind(&self) -> ImportErrorKind {
+        match self {
+            BlockImportError::BlockNumber { .. } | BlockImportError::BlockHash { .. } => ImportErrorKind::HeaderHash,
+            BlockImportError::ParentHash { .. } => ImportErrorKind::ParentHash,
+            BlockImportError::GlobalStateRoot { .. } => ImportErrorKind::StateRoot,
+            BlockImportError::InternalDb { .. } | BlockImportError::Internal(_) => ImportErrorKind::Internal,
+            _ => ImportErrorKind::BodyVerification,
+        }
+    }
+}
+
+/// Which validation step rejected a block, see [`ImportError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportErrorKind {
+    /// The block hash or block number in the header does not match.
+    HeaderHash,
+    /// The parent hash in the header is not the hash of the local block below it: the chain diverged from ours.
+    ParentHash,
+    /// The global state root after applying the block does not match its header.
+    StateRoot,
+    /// The transactions, events, receipts, state diff or classes do not match their header commitments.
+    BodyVerification,
+    /// The block could not be converted from the format it was received in.
+    BodyConversion,
+    /// Database or other local failure, unrelated to the block itself.
+    Internal,
+}
+
+impl std::fmt::Display for ImportErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::HeaderHash => write!(f, "header hash mismatch"),
+            Self::ParentHash => write!(f, "parent hash mismatch"),
+            Self::StateRoot => write!(f, "state root mismatch"),
+            Self::BodyVerification => write!(f, "body verification failure"),
+            Self::BodyConversion => write!(f, "body conversion failure"),
+            Self::Internal => write!(f, "internal error"),
+        }
+    }
+}
+
+/// A block rejected during sync. Forward sync pipelines surface this error (wrapped in an [`anyhow::Error`]), so
+/// that the sync controller can tell which block failed and why.
+#[derive(Debug, thiserror::Error)]
+#[error("Importing block #{block_n} failed: {kind}")]
+pub struct ImportError {
+    pub block_n: u64,
+    pub kind: ImportErrorKind,
+    #[source]
+    pub error: anyhow::Error,
+}
+
+impl ImportError {
+    /// Uses the block number carried by the error when there is one, `block_n` otherwise.
+    pub fn new(block_n: u64, error: BlockImportError) -> Self {
+        let block_n = match &error {
+            BlockImportError::GlobalStateRoot { block_n, .. } => *block_n,
+            _ => block_n,
+        };
+        Self { block_n, kind: error.kind(), error: error.into() }
+    }
+
+    pub fn body_conversion(block_n: u64, error: anyhow::Error) -> Self {
+        Self { block_n, kind: ImportErrorKind::BodyConversion, error }
+    }
+}
+
+/// CSV file the global trie timings are appended to, see [`BlockValidationConfig::global_trie_timings`].
+struct GlobalTrieTimings(BufWriter<File>);
+
+impl GlobalTrieTimings {
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Opening global trie timings file {}", path.display()))?;
+        let is_empty = file.metadata().context("Reading global trie timings file metadata")?.len() == 0;
+
+        let mut this = Self(BufWriter::new(file));
+        if is_empty {
+            writeln!(this.0, "block_n,micros,storage_updates").context("Writing global trie timings header")?;
+        }
+        Ok(this)
+    }
+
+    fn record(&mut self, block_n: u64, duration: Duration, storage_updates: usize) -> anyhow::Result<()> {
+        writeln!(self.0, "{block_n},{},{storage_updates}", duration.as_micros())
+            .and_then(|_| self.0.flush())
+            .context("Writing global trie timings")
+    }
 }
 
 /// Shared verification & saving logic between gateway and (yet-to-be-merged) p2p.
@@ -217,6 +348,29 @@ impl BlockImporterCtx {
         Ok(())
     }
 
+    /// Checks that the block follows the local chain. A missing parent block is only accepted with
+    /// [`BlockValidationConfig::trust_parent_hash`], when starting the sync at some height.
+    pub fn verify_parent_hash(&self, block_n: u64, parent_hash: Felt) -> Result<(), BlockImportError> {
+        if self.config.no_check {
+            return Ok(());
+        }
+        let expected = match block_n.checked_sub(1) {
+            Some(parent_n) => self.db.get_block_hash(&RawDbBlockId::Number(parent_n)).map_err(|error| {
+                BlockImportError::InternalDb { error, context: format!("Getting block hash for {parent_n}").into() }
+            })?,
+            None => Some(Felt::ZERO),
+        };
+        match expected {
+            Some(expected) if expected != parent_hash => {
+                Err(BlockImportError::ParentHash { got: parent_hash, expected })
+            }
+            None if !self.config.trust_parent_hash => {
+                Err(anyhow::anyhow!("Parent of block #{block_n} not found in the local chain").into())
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub fn save_header(&self, block_n: u64, signed_header: BlockHeaderWithSignatures) -> Result<(), BlockImportError> {
         self.db.store_block_header(signed_header).map_err(|error| BlockImportError::InternalDb {
             error,
@@ -530,6 +684,17 @@ impl BlockImporterCtx {
         mut block_range: Range<u64>,
         state_diffs: Vec<StateDiff>,
     ) -> Result<(), BlockImportError> {
+        if self.config.trust_global_trie {
+            tracing::debug!("Trusting global trie for blocks {block_range:?}");
+            return Ok(());
+        }
+
+        // Apply the blocks skipped while trusting the global trie first.
+        let next_to_import = self.db.head_status().global_trie.next();
+        if next_to_import < block_range.start {
+            self.catch_up_global_trie(next_to_import..block_range.start)?;
+        }
+
         // don't re-import the blocks we've already imported.
         let next_to_import = self.db.head_status().global_trie.next();
         let already_imported_count = next_to_import.saturating_sub(block_range.start);
@@ -540,37 +705,95 @@ impl BlockImporterCtx {
             return Ok(()); // range is empty
         };
 
+        if self.config.verify_each_global_state_root || self.config.global_trie_timings.is_some() {
+            let mut timings = self.config.global_trie_timings.as_deref().map(GlobalTrieTimings::open).transpose()?;
+
+            // Apply the state diffs one by one, so that we can report the first diverging block.
+            for (block_n, state_diff) in block_range.zip(state_diffs) {
+                let storage_updates =
+                    state_diff.storage_diffs.iter().map(|diff| diff.storage_entries.len()).sum::<usize>();
+                let start = Instant::now();
+                let got = self.db.apply_to_global_trie(block_n, [state_diff]).map_err(|error| {
+                    BlockImportError::InternalDb { error, context: "Applying state diff to global trie".into() }
+                })?;
+                if let Some(timings) = &mut timings {
+                    timings.record(block_n, start.elapsed(), storage_updates)?;
+                }
+                if self.config.verify_each_global_state_root || block_n == last_block_n {
+                    self.verify_global_state_root(block_n, got)?;
+                }
+            }
+            return Ok(());
+        }
+
         let got = self.db.apply_to_global_trie(block_range.start, state_diffs).map_err(|error| {
             BlockImportError::InternalDb { error, context: "Applying state diff to global trie".into() }
         })?;
 
         // Sanity check: verify state root.
-        if !self.config.no_check {
-            let expected = self
-                .db
-                .get_block_info(&RawDbBlockId::Number(last_block_n))
-                .map_err(|error| BlockImportError::InternalDb {
-                    error,
-                    context: format!("Cannot find block info for block #{last_block_n}").into(),
-                })?
-                .context("Block header cannot be found")?
-                .into_closed()
-                .context("Block is pending")?
-                .header
-                .global_state_root;
-
-            if expected != got {
-                return Err(BlockImportError::GlobalStateRoot { got, expected });
-            }
+        self.verify_global_state_root(last_block_n, got)
+    }
+
+    /// Applies the state diffs saved in the database to the global trie. Does nothing if the state diff of the first
+    /// block is not in the database, which is the case when the node was started at a later block.
+    fn catch_up_global_trie(&self, block_range: Range<u64>) -> Result<(), BlockImportError> {
+        const CATCH_UP_BATCH_SIZE: u64 = 64;
+
+        let get_state_diff = |block_n| {
+            self.db.get_block_state_diff(&RawDbBlockId::Number(block_n)).map_err(|error| BlockImportError::InternalDb {
+                error,
+                context: format!("Getting state diff for block #{block_n}").into(),
+            })
+        };
+
+        if get_state_diff(block_range.start)?.is_none() {
+            return Ok(());
+        }
+
+        tracing::info!("Catching up on the global trie for blocks {block_range:?}");
+        for start in block_range.clone().step_by(CATCH_UP_BATCH_SIZE as _) {
+            let batch = start..cmp::min(start + CATCH_UP_BATCH_SIZE, block_range.end);
+            let state_diffs = batch
+                .clone()
+                .map(|block_n| {
+                    Ok(get_state_diff(block_n)?.with_context(|| format!("No state diff for block #{block_n}"))?)
+                })
+                .collect::<Result<_, BlockImportError>>()?;
+            self.apply_to_global_trie(batch, state_diffs)?;
+        }
+        Ok(())
+    }
+
+    fn verify_global_state_root(&self, block_n: u64, got: Felt) -> Result<(), BlockImportError> {
+        if self.config.no_check {
+            return Ok(());
         }
 
+        let expected = self
+            .db
+            .get_block_info(&RawDbBlockId::Number(block_n))
+            .map_err(|error| BlockImportError::InternalDb {
+                error,
+                context: format!("Cannot find block info for block #{block_n}").into(),
+            })?
+            .context("Block header cannot be found")?
+            .into_closed()
+            .context("Block is pending")?
+            .header
+            .global_state_root;
+
+        if expected != got {
+            return Err(BlockImportError::GlobalStateRoot { block_n, got, expected });
+        }
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{BlockImportError, BlockImporter, BlockImporterCtx, BlockValidationConfig};
+    use super::{
+        BlockImportError, BlockImporter, BlockImporterCtx, BlockValidationConfig, ImportError, ImportErrorKind,
+    };
     use assert_matches::assert_matches;
     use mc_db::MadaraBackend;
     use mp_block::{BlockHeaderWithSignatures, FullBlock, Header};
@@ -610,7 +833,7 @@ mod tests {
             felt!("0xb"), // A non-zero global state root
             StateDiff::default(), // Empty state diff
             // Expected result: a GlobalStateRoot error due to mismatch
-            Err(BlockImportError::GlobalStateRoot { expected: felt!("0xb"), got: felt!("0x0") })
+            Err(BlockImportError::GlobalStateRoot { block_n: 0, expected: felt!("0xb"), got: felt!("0x0") })
         )]
     #[case::empty_state_diff(
             felt!("0x0"), // Zero global state root
@@ -647,6 +870,192 @@ mod tests {
         assert_eq!(expected_result.map_err(|e| format!("{e:#}")), result.map_err(|e| format!("{e:#}")),)
     }
 
+    #[rstest]
+    #[case::check_last_block_only(false, Ok(()))]
+    #[case::check_each_block(
+            true,
+            Err(BlockImportError::GlobalStateRoot { block_n: 0, expected: felt!("0xb"), got: felt!("0x0") })
+        )]
+    #[tokio::test]
+    async fn test_update_tries_verify_each_global_state_root(
+        #[case] verify_each_global_state_root: bool,
+        #[case] expected_result: Result<(), BlockImportError>,
+    ) {
+        // GIVEN: block 0 has a wrong global state root, but block 1 has the right one
+        let backend = MadaraBackend::open_for_testing(Arc::new(ChainConfig::madara_test()));
+        for (block_n, global_state_root) in [(0, felt!("0xb")), (1, felt!("0x0"))] {
+            backend
+                .store_block_header(BlockHeaderWithSignatures {
+                    block_hash: Felt::from(block_n),
+                    consensus_signatures: vec![],
+                    header: Header { global_state_root, block_number: block_n, ..Default::default() },
+                })
+                .unwrap();
+        }
+
+        let validation = BlockValidationConfig::default().verify_each_global_state_root(verify_each_global_state_root);
+        let importer = BlockImporter::new(backend, validation);
+
+        // WHEN: We apply both blocks in a single batch
+        let result = importer.ctx().apply_to_global_trie(0..2, vec![StateDiff::default(), StateDiff::default()]);
+
+        // THEN: the divergence at block 0 is only caught when checking every block
+        assert_eq!(expected_result.map_err(|e| format!("{e:#}")), result.map_err(|e| format!("{e:#}")),)
+    }
+
+    #[tokio::test]
+    async fn test_update_tries_global_trie_timings() {
+        let backend = MadaraBackend::open_for_testing(Arc::new(ChainConfig::madara_test()));
+        let state_diffs = vec![
+            StateDiff::default(),
+            StateDiff {
+                storage_diffs: vec![ContractStorageDiffItem {
+                    address: felt!("0x1"),
+                    storage_entries: vec![
+                        StorageEntry { key: felt!("0x1"), value: felt!("0x1") },
+                        StorageEntry { key: felt!("0x2"), value: felt!("0x2") },
+                    ],
+                }],
+                ..Default::default()
+            },
+        ];
+        let timings = tempfile::NamedTempFile::new().unwrap();
+
+        // The state roots are not checked here, only the timings are.
+        let validation = BlockValidationConfig::default()
+            .all_verifications_disabled(true)
+            .global_trie_timings(Some(timings.path().into()));
+        let importer = BlockImporter::new(backend, validation);
+        importer.ctx().apply_to_global_trie(0..2, state_diffs).unwrap();
+
+        let timings = std::fs::read_to_string(timings.path()).unwrap();
+        let rows = timings.lines().map(|line| line.split(',').collect::<Vec<_>>()).collect::<Vec<_>>();
+        assert_eq!(rows.len(), 3, "{timings}");
+        assert_eq!(rows[0], ["block_n", "micros", "storage_updates"]);
+        assert_eq!((rows[1][0], rows[1][2]), ("0", "0"));
+        assert_eq!((rows[2][0], rows[2][2]), ("1", "2"));
+        assert!(rows[1..].iter().all(|row| row[1].parse::<u128>().is_ok()), "{timings}");
+    }
+
+    #[rstest]
+    #[case::catch_up_ok(felt!("0x0"), Ok(()))]
+    #[case::catch_up_mismatch(
+            felt!("0xb"),
+            Err(BlockImportError::GlobalStateRoot { block_n: 0, expected: felt!("0xb"), got: felt!("0x0") })
+        )]
+    #[tokio::test]
+    async fn test_update_tries_trust_global_trie(
+        #[case] block_0_global_state_root: Felt,
+        #[case] expected_result: Result<(), BlockImportError>,
+    ) {
+        let backend = MadaraBackend::open_for_testing(Arc::new(ChainConfig::madara_test()));
+        for (block_n, global_state_root) in [(0, block_0_global_state_root), (1, felt!("0x0"))] {
+            backend
+                .store_block_header(BlockHeaderWithSignatures {
+                    block_hash: Felt::from(block_n),
+                    consensus_signatures: vec![],
+                    header: Header { global_state_root, block_number: block_n, ..Default::default() },
+                })
+                .unwrap();
+            backend.store_state_diff(block_n, StateDiff::default()).unwrap();
+        }
+
+        // GIVEN: block 0 was imported while trusting the global trie
+        let trusting = BlockImporter::new(backend.clone(), BlockValidationConfig::default().trust_global_trie(true));
+        trusting.ctx().apply_to_global_trie(0..1, vec![StateDiff::default()]).unwrap();
+        assert_eq!(backend.head_status().global_trie.current(), None);
+
+        // WHEN: block 1 is imported without trusting the global trie
+        let importer = BlockImporter::new(backend.clone(), BlockValidationConfig::default());
+        let result = importer.ctx().apply_to_global_trie(1..2, vec![StateDiff::default()]);
+
+        // THEN: block 0 is applied and verified first
+        assert_eq!(expected_result.map_err(|e| format!("{e:#}")), result.map_err(|e| format!("{e:#}")));
+    }
+
+    #[rstest]
+    #[case::header_hash(
+        BlockImportError::BlockHash { got: felt!("0x1"), expected: felt!("0x2") },
+        ImportErrorKind::HeaderHash,
+        5
+    )]
+    #[case::parent_hash(
+        BlockImportError::ParentHash { got: felt!("0x1"), expected: felt!("0x2") },
+        ImportErrorKind::ParentHash,
+        5
+    )]
+    #[case::state_root(
+        BlockImportError::GlobalStateRoot { block_n: 7, got: felt!("0x1"), expected: felt!("0x2") },
+        ImportErrorKind::StateRoot,
+        7
+    )]
+    #[case::body_verification(
+        BlockImportError::TransactionCommitment { got: felt!("0x1"), expected: felt!("0x2") },
+        ImportErrorKind::BodyVerification,
+        5
+    )]
+    #[case::internal(BlockImportError::Internal(anyhow::anyhow!("oops")), ImportErrorKind::Internal, 5)]
+    fn test_import_error_kind(
+        #[case] error: BlockImportError,
+        #[case] expected_kind: ImportErrorKind,
+        #[case] expected_block_n: u64,
+    ) {
+        let err = ImportError::new(5, error);
+        assert_eq!(err.kind, expected_kind);
+        assert_eq!(err.block_n, expected_block_n);
+        // The error survives being wrapped into an anyhow error by the pipeline.
+        let err = anyhow::Error::from(err).context("Verifying block");
+        assert_eq!(err.downcast_ref::<ImportError>().map(|err| err.kind), Some(expected_kind));
+    }
+
+    #[rstest]
+    #[case::follows(felt!("0x10"), BlockValidationConfig::default(), Ok(()))]
+    #[case::diverges(
+        felt!("0x99"),
+        BlockValidationConfig::default(),
+        Err(BlockImportError::ParentHash { got: felt!("0x99"), expected: felt!("0x10") })
+    )]
+    #[case::diverges_no_check(felt!("0x99"), BlockValidationConfig::default().all_verifications_disabled(true), Ok(()))]
+    fn test_verify_parent_hash(
+        #[case] parent_hash: Felt,
+        #[case] validation: BlockValidationConfig,
+        #[case] expected_result: Result<(), BlockImportError>,
+    ) {
+        let backend = MadaraBackend::open_for_testing(Arc::new(ChainConfig::madara_test()));
+        backend
+            .store_block_header(BlockHeaderWithSignatures {
+                block_hash: felt!("0x10"),
+                consensus_signatures: vec![],
+                header: Header { block_number: 0, ..Default::default() },
+            })
+            .unwrap();
+        let importer = BlockImporter::new(backend, validation).ctx();
+
+        let result = importer.verify_parent_hash(1, parent_hash);
+        assert_eq!(expected_result.map_err(|e| format!("{e:#}")), result.map_err(|e| format!("{e:#}")));
+    }
+
+    #[rstest]
+    #[case::genesis(0, BlockValidationConfig::default(), true)]
+    #[case::missing_parent(5, BlockValidationConfig::default(), false)]
+    #[case::missing_parent_trusted(5, BlockValidationConfig::default().trust_parent_hash(true), true)]
+    fn test_verify_parent_hash_without_parent(
+        #[case] block_n: u64,
+        #[case] validation: BlockValidationConfig,
+        #[case] is_ok: bool,
+    ) {
+        let backend = MadaraBackend::open_for_testing(Arc::new(ChainConfig::madara_test()));
+        let importer = BlockImporter::new(backend, validation).ctx();
+        assert_eq!(importer.verify_parent_hash(block_n, Felt::ZERO).is_ok(), is_ok);
+    }
+
+    #[rstest]
+    fn test_import_error_body_conversion() {
+        let err = ImportError::body_conversion(5, anyhow::anyhow!("Parsing gateway block"));
+        assert_eq!(err.kind, ImportErrorKind::BodyConversion);
+        assert_eq!(err.block_n, 5);
+    }
+
     struct Ctx {
         importer: BlockImporterCtx,
         block_n: u64,
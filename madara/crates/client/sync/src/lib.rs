@@ -7,7 +7,7 @@ mod sync;
 mod tests;
 mod util;
 
-pub use sync::SyncControllerConfig;
+pub use sync::{SyncControllerConfig, SyncPhase, SyncPhaseReceiver, SyncPhaseSender};
 
 pub mod gateway;
 pub mod import;
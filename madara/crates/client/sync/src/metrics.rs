@@ -33,6 +33,10 @@ pub struct SyncMetrics {
     // gas price is also define in eth/client.rs but this would be the gas used in the block and it's price
     pub l1_gas_price_wei: Histogram<f64>,
     pub l1_gas_price_strk: Histogram<f64>,
+
+    // Sync controller metrics
+    pub probe_latency: Histogram<f64>,
+    pub l1_head_lag: Histogram<f64>,
 }
 
 impl SyncMetrics {
@@ -108,6 +112,20 @@ impl SyncMetrics {
             "".to_string(),
         );
 
+        let probe_latency = register_histogram_metric_instrument(
+            &block_meter,
+            "probe_latency".to_string(),
+            "Gauge for madara sync probe round-trip latency in seconds".to_string(),
+            "".to_string(),
+        );
+
+        let l1_head_lag = register_histogram_metric_instrument(
+            &block_meter,
+            "l1_head_lag".to_string(),
+            "Gauge for the number of blocks madara L2 sync is behind the L1 head".to_string(),
+            "".to_string(),
+        );
+
         Self {
             counter: ThroughputCounter::new(Duration::from_secs(5 * 60)),
 
@@ -127,6 +145,9 @@ impl SyncMetrics {
 
             l1_gas_price_wei,
             l1_gas_price_strk,
+
+            probe_latency,
+            l1_head_lag,
         }
     }
 
@@ -16,6 +16,9 @@ pub struct ThrottledRepeatedFuture<T: Clone> {
     make_future: Box<dyn FnMut(Option<T>) -> InnerFut<T> + Send>,
     wait: Option<Instant>,
     wait_duration: Duration,
+    started_at: Option<Instant>,
+    last_duration: Option<Duration>,
+    last_failed: bool,
 }
 
 impl<T: Clone> ThrottledRepeatedFuture<T> {
@@ -24,7 +27,16 @@ impl<T: Clone> ThrottledRepeatedFuture<T> {
         F: FnMut(Option<T>) -> Fut + Send + 'static,
         Fut: Future<Output = anyhow::Result<Option<T>>> + Send + 'static,
     {
-        Self { last_val: None, future: None, make_future: Box::new(move |v| f(v).boxed()), wait_duration, wait: None }
+        Self {
+            last_val: None,
+            future: None,
+            make_future: Box::new(move |v| f(v).boxed()),
+            wait_duration,
+            wait: None,
+            started_at: None,
+            last_duration: None,
+            last_failed: false,
+        }
     }
 
     pub async fn run(&mut self) -> anyhow::Result<Option<T>> {
@@ -32,20 +44,36 @@ impl<T: Clone> ThrottledRepeatedFuture<T> {
             tokio::time::sleep_until(wait).await;
             self.wait = None;
         }
+        let started_at = *self.started_at.get_or_insert_with(Instant::now);
         let fut = self.future.get_or_insert_with(|| (self.make_future)(self.last_val.clone()));
         let res = fut.await;
         self.future = None;
-        let res = res?;
+        self.started_at = None;
+        // Failed calls are throttled too, and leave the last value as is.
         self.wait = Some(Instant::now() + self.wait_duration);
+        self.last_failed = res.is_err();
+        let res = res?;
+        self.last_duration = Some(started_at.elapsed());
 
         self.last_val = res.clone();
         Ok(res)
     }
 
+    /// Value returned by the last successful call.
     pub fn last_val(&self) -> Option<T> {
         self.last_val.clone()
     }
 
+    /// How long the last successful call took, excluding the throttling wait.
+    pub fn last_duration(&self) -> Option<Duration> {
+        self.last_duration
+    }
+
+    /// Whether the last call returned an error.
+    pub fn last_failed(&self) -> bool {
+        self.last_failed
+    }
+
     pub fn is_running(&self) -> bool {
         self.future.is_some()
     }
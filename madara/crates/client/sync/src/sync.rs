@@ -1,5 +1,13 @@
-use crate::{metrics::SyncMetrics, probe::ThrottledRepeatedFuture, util::ServiceStateSender};
-use futures::{future::OptionFuture, Future};
+use crate::{
+    import::{ImportError, ImportErrorKind},
+    metrics::SyncMetrics,
+    probe::ThrottledRepeatedFuture,
+    util::ServiceStateSender,
+};
+use futures::{
+    future::{self, OptionFuture},
+    Future, FutureExt,
+};
 use mc_db::{MadaraBackend, SyncStatus};
 use mc_settlement_client::state_update::{L1HeadReceiver, StateUpdate};
 use mp_gateway::block::ProviderBlockHeader;
@@ -29,6 +37,19 @@ pub enum ServiceEvent {
     SyncingTo { target: u64 },
 }
 
+/// Whether the node is still importing blocks towards the sync target, or has imported all of them.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SyncPhase {
+    /// `behind` blocks remain to be imported before reaching the sync target.
+    CatchingUp { behind: u64 },
+    /// Every block up to the sync target has been imported.
+    AtTip,
+}
+
+/// Notified on every [`SyncPhase`] transition. The value is [`None`] until the sync target is known.
+pub type SyncPhaseSender = tokio::sync::watch::Sender<Option<SyncPhase>>;
+pub type SyncPhaseReceiver = tokio::sync::watch::Receiver<Option<SyncPhase>>;
+
 pub struct SyncControllerConfig {
     pub l1_head_recv: L1HeadReceiver,
     /// Stop the sync process at this block.
@@ -44,6 +65,9 @@ pub struct SyncControllerConfig {
     /// [`Self::stop_at_block_n`] is set.
     pub stop_on_sync: bool,
 
+    /// Notified whenever the node goes from catching up to being at the tip of the chain, and back.
+    pub sync_phase_sender: SyncPhaseSender,
+
     /// For testing purposes, you can subscribe to the service state. This is used in tests
     /// to know when the service is idling.
     pub service_state_sender: ServiceStateSender<ServiceEvent>,
@@ -68,6 +92,9 @@ impl SyncControllerConfig {
     pub fn service_state_sender(self, service_state_sender: ServiceStateSender<ServiceEvent>) -> Self {
         Self { service_state_sender, ..self }
     }
+    pub fn sync_phase_sender(self, sync_phase_sender: SyncPhaseSender) -> Self {
+        Self { sync_phase_sender, ..self }
+    }
 }
 
 impl Default for SyncControllerConfig {
@@ -80,6 +107,8 @@ impl Default for SyncControllerConfig {
             global_stop_on_sync: false,
             stop_on_sync: false,
             no_pending_block: false,
+            // Nobody is subscribed to this channel unless a sender is given.
+            sync_phase_sender: tokio::sync::watch::channel(None).0,
             service_state_sender: Default::default(),
         }
     }
@@ -89,7 +118,8 @@ pub struct SyncController<P: ForwardPipeline> {
     forward_pipeline: P,
     config: SyncControllerConfig,
     current_l1_head: Option<StateUpdate>,
-    probe: ThrottledRepeatedFuture<ProviderBlockHeader>,
+    /// Every probe is run concurrently, and the highest block any of them knows about is used as the sync target.
+    probes: Vec<ThrottledRepeatedFuture<ProviderBlockHeader>>,
     sync_metrics: SyncMetrics,
     status: Option<ServiceEvent>,
     get_pending_block: Option<ThrottledRepeatedFuture<()>>,
@@ -110,6 +140,18 @@ impl<P: ForwardPipeline> SyncController<P> {
         probe: ThrottledRepeatedFuture<ProviderBlockHeader>,
         config: SyncControllerConfig,
         get_pending_block: Option<ThrottledRepeatedFuture<()>>,
+    ) -> Self {
+        Self::new_with_probes(backend, forward_pipeline, vec![probe], config, get_pending_block)
+    }
+
+    /// Make a sync controller that aggregates multiple probes, such as a gateway probe and a p2p probe. The sync
+    /// target will be the highest block returned by any of the probes, and a failing probe does not stop the others.
+    pub(crate) fn new_with_probes(
+        backend: Arc<MadaraBackend>,
+        forward_pipeline: P,
+        probes: Vec<ThrottledRepeatedFuture<ProviderBlockHeader>>,
+        config: SyncControllerConfig,
+        get_pending_block: Option<ThrottledRepeatedFuture<()>>,
     ) -> Self {
         Self {
             sync_metrics: SyncMetrics::register(forward_pipeline.next_input_block_n()),
@@ -117,7 +159,7 @@ impl<P: ForwardPipeline> SyncController<P> {
             forward_pipeline,
             config,
             current_l1_head: None,
-            probe,
+            probes,
             status: None,
             backend,
         }
@@ -145,10 +187,15 @@ impl<P: ForwardPipeline> SyncController<P> {
         Ok(())
     }
 
+    /// Highest block header returned by any of the probes.
+    fn probe_highest_block(&self) -> Option<ProviderBlockHeader> {
+        self.probes.iter().filter_map(|probe| probe.last_val()).max_by_key(|v| v.block_number)
+    }
+
     fn target_height(&self) -> Option<u64> {
         let mut target_block = cmp::max(
             self.current_l1_head.as_ref().and_then(|h| h.block_number),
-            self.probe.last_val().map(|v| v.block_number),
+            self.probe_highest_block().map(|v| v.block_number),
         );
 
         // Bound by stop_at_block_n
@@ -161,9 +208,22 @@ impl<P: ForwardPipeline> SyncController<P> {
         target_block
     }
 
+    pub(crate) fn update_sync_phase(&mut self) {
+        let Some(target_height) = self.target_height() else { return };
+        let imported = self.forward_pipeline.latest_block().map_or(0, |block_n| block_n + 1);
+        let behind = (target_height + 1).saturating_sub(imported);
+        let phase = if behind == 0 { SyncPhase::AtTip } else { SyncPhase::CatchingUp { behind } };
+        self.config.sync_phase_sender.send_if_modified(|current| {
+            let modified = *current != Some(phase);
+            *current = Some(phase);
+            modified
+        });
+    }
+
     async fn run_inner(&mut self) -> anyhow::Result<()> {
         loop {
             let target_height = self.target_height();
+            self.update_sync_phase();
 
             let can_run_pipeline = !self.forward_pipeline.is_empty()
                 || target_height.is_some_and(|b| b >= self.forward_pipeline.next_input_block_n());
@@ -174,7 +234,7 @@ impl<P: ForwardPipeline> SyncController<P> {
                 self.forward_pipeline.next_input_block_n()
             );
 
-            let probe_height = if let Some(v) = self.probe.last_val() {
+            let probe_height = if let Some(v) = self.probe_highest_block() {
                 self.backend
                     .set_sync_status(SyncStatus::Running {
                         highest_block_n: v.block_number,
@@ -186,6 +246,11 @@ impl<P: ForwardPipeline> SyncController<P> {
                 None
             };
 
+            if let Some(l1_block_n) = self.current_l1_head.as_ref().and_then(|h| h.block_number) {
+                let lag = (l1_block_n + 1).saturating_sub(self.forward_pipeline.next_input_block_n());
+                self.sync_metrics.l1_head_lag.record(lag as f64, &[]);
+            }
+
             let target = target_height.filter(|_| can_run_pipeline);
 
             if let Some(target) = target {
@@ -213,17 +278,38 @@ impl<P: ForwardPipeline> SyncController<P> {
                 Some(res) = OptionFuture::from(
                     target.map(|target| self.forward_pipeline.run(target, probe_height, &mut self.sync_metrics))
                 ) => {
+                    if let Some(err) = res.as_ref().err().and_then(|err| err.downcast_ref::<ImportError>()) {
+                        report_import_error(err);
+                    }
                     res?;
                 }
-                res = self.probe.run() => {
-                    let new_probe_height = res?.map(|v| v.block_number);
+                // Run all the probes concurrently, handling whichever finishes first.
+                Some((res, probe_index)) = OptionFuture::from((!self.probes.is_empty()).then(|| {
+                    future::select_all(self.probes.iter_mut().map(|probe| Box::pin(probe.run())))
+                        .map(|(res, probe_index, _)| (res, probe_index))
+                })) => {
+                    if let Err(err) = res {
+                        // Without any known block there is nothing to sync towards, the probes are most likely
+                        // misconfigured.
+                        if self.probe_highest_block().is_none() && self.probes.iter().all(|probe| probe.last_failed()) {
+                            return Err(err.context("Probing the chain head"));
+                        }
+                        // The other probes keep running. A failed probe does not count as an unchanged chain head for
+                        // stop_on_sync.
+                        tracing::warn!("Probing the chain head failed, keeping the last known one: {err:#}");
+                        continue;
+                    }
+                    let new_probe_height = self.probe_highest_block().map(|v| v.block_number);
+                    if let Some(latency) = self.probes[probe_index].last_duration() {
+                        self.sync_metrics.probe_latency.record(latency.as_secs_f64(), &[]);
+                    }
                     if self.config.stop_at_block_n.is_none()
                         && !can_run_pipeline
                         && self.config.stop_on_sync
                         && probe_height == new_probe_height
                         && !self.pending_block_task_is_running()
                     {
-                        // Probe returned the same thing as last time, and we cannot run the pipeline.
+                        // Probes returned the same thing as last time, and we cannot run the pipeline.
                         // This is the exit condition when stop_on_sync is enabled,
                         // except if there is a stop_at_block_n.
                         break Ok(());
@@ -265,3 +351,14 @@ impl<P: ForwardPipeline> SyncController<P> {
         );
     }
 }
+
+fn report_import_error(err: &ImportError) {
+    if err.kind == ImportErrorKind::ParentHash {
+        tracing::error!(
+            "❗ Block #{} does not follow the local chain, the chain reorged and rolling back is not supported",
+            err.block_n
+        );
+    } else {
+        tracing::error!("❗ Block #{} was rejected ({})", err.block_n, err.kind);
+    }
+}
@@ -0,0 +1,331 @@
+//! Checks the [`SyncController`] select loop in isolation, with a scripted pipeline, probe and pending block task.
+
+use crate::{
+    metrics::SyncMetrics,
+    probe::ThrottledRepeatedFuture,
+    sync::{ForwardPipeline, SyncController},
+    SyncControllerConfig, SyncPhase,
+};
+use mc_db::MadaraBackend;
+use mc_settlement_client::state_update::StateUpdate;
+use mp_chain_config::ChainConfig;
+use mp_gateway::block::ProviderBlockHeader;
+use mp_utils::service::ServiceContext;
+use rstest::rstest;
+use starknet_core::types::Felt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Event {
+    PipelineRun { target: u64, probe_height: Option<u64> },
+    Probe,
+    PendingBlock { next_input_block_n: u64 },
+}
+
+type EventLog = Arc<Mutex<Vec<Event>>>;
+
+/// Imports a single block per run, so that the controller has to call it repeatedly to reach the target.
+struct MockPipeline {
+    next_input_block_n: Arc<Mutex<u64>>,
+    log: EventLog,
+}
+
+impl ForwardPipeline for MockPipeline {
+    async fn run(
+        &mut self,
+        target_block_n: u64,
+        probe_height: Option<u64>,
+        _metrics: &mut SyncMetrics,
+    ) -> anyhow::Result<()> {
+        self.log.lock().unwrap().push(Event::PipelineRun { target: target_block_n, probe_height });
+        *self.next_input_block_n.lock().unwrap() += 1;
+        Ok(())
+    }
+    fn next_input_block_n(&self) -> u64 {
+        *self.next_input_block_n.lock().unwrap()
+    }
+    fn show_status(&self) {}
+    fn is_empty(&self) -> bool {
+        true
+    }
+    fn latest_block(&self) -> Option<u64> {
+        self.next_input_block_n().checked_sub(1)
+    }
+}
+
+/// Always returns the same chain head.
+fn mock_probe(block_n: u64, log: EventLog, wait_duration: Duration) -> ThrottledRepeatedFuture<ProviderBlockHeader> {
+    ThrottledRepeatedFuture::new(
+        move |_| {
+            log.lock().unwrap().push(Event::Probe);
+            async move { Ok(Some(ProviderBlockHeader { block_number: block_n, block_hash: Felt::from(block_n) })) }
+        },
+        wait_duration,
+    )
+}
+
+/// Never reaches the chain head.
+fn failing_probe(log: EventLog) -> ThrottledRepeatedFuture<ProviderBlockHeader> {
+    ThrottledRepeatedFuture::new(
+        move |_| {
+            log.lock().unwrap().push(Event::Probe);
+            async move { Err(anyhow::anyhow!("Probe unavailable")) }
+        },
+        Duration::from_millis(10),
+    )
+}
+
+/// Records the pipeline position every time it is called, and never finds a pending block.
+fn mock_pending_block(next_input_block_n: Arc<Mutex<u64>>, log: EventLog) -> ThrottledRepeatedFuture<()> {
+    ThrottledRepeatedFuture::new(
+        move |_| {
+            let next_input_block_n = *next_input_block_n.lock().unwrap();
+            log.lock().unwrap().push(Event::PendingBlock { next_input_block_n });
+            async move { Ok(None) }
+        },
+        Duration::from_millis(10),
+    )
+}
+
+struct TestContext {
+    backend: Arc<MadaraBackend>,
+    next_input_block_n: Arc<Mutex<u64>>,
+    log: EventLog,
+}
+
+impl TestContext {
+    fn new() -> Self {
+        Self {
+            backend: MadaraBackend::open_for_testing(Arc::new(ChainConfig::madara_test())),
+            next_input_block_n: Default::default(),
+            log: Default::default(),
+        }
+    }
+
+    fn controller(
+        &self,
+        probe_block_n: u64,
+        config: SyncControllerConfig,
+        with_pending_block: bool,
+    ) -> SyncController<MockPipeline> {
+        let pipeline = MockPipeline { next_input_block_n: self.next_input_block_n.clone(), log: self.log.clone() };
+        SyncController::new(
+            self.backend.clone(),
+            pipeline,
+            mock_probe(probe_block_n, self.log.clone(), Duration::from_millis(10)),
+            config,
+            with_pending_block.then(|| mock_pending_block(self.next_input_block_n.clone(), self.log.clone())),
+        )
+    }
+
+    fn controller_with_probes(
+        &self,
+        probes: Vec<ThrottledRepeatedFuture<ProviderBlockHeader>>,
+        config: SyncControllerConfig,
+    ) -> SyncController<MockPipeline> {
+        let pipeline = MockPipeline { next_input_block_n: self.next_input_block_n.clone(), log: self.log.clone() };
+        SyncController::new_with_probes(self.backend.clone(), pipeline, probes, config, None)
+    }
+
+    fn pipeline_targets(&self) -> Vec<u64> {
+        self.log
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|ev| match ev {
+                Event::PipelineRun { target, .. } => Some(*target),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[rstest]
+#[case::probe_only(None, 5, None, 5)]
+#[case::l1_behind_probe(Some(3), 5, None, 5)]
+#[case::l1_ahead_of_probe(Some(8), 5, None, 8)]
+#[case::bounded_by_stop_at(Some(8), 5, Some(6), 6)]
+#[case::stop_at_below_probe(None, 5, Some(2), 2)]
+#[tokio::test]
+async fn test_target_height(
+    #[case] l1_head: Option<u64>,
+    #[case] probe_block_n: u64,
+    #[case] stop_at_block_n: Option<u64>,
+    #[case] expected_target: u64,
+) {
+    let ctx = TestContext::new();
+    let (l1_snd, l1_recv) = tokio::sync::watch::channel(None);
+    if let Some(block_n) = l1_head {
+        l1_snd
+            .send(Some(StateUpdate { block_number: Some(block_n), global_root: Felt::ZERO, block_hash: Felt::ZERO }))
+            .unwrap();
+    }
+
+    let mut sync = ctx.controller(
+        probe_block_n,
+        SyncControllerConfig::default().l1_head_recv(l1_recv).stop_on_sync(true).stop_at_block_n(stop_at_block_n),
+        false,
+    );
+    tokio::time::timeout(Duration::from_secs(10), sync.run(ServiceContext::default())).await.unwrap().unwrap();
+
+    let targets = ctx.pipeline_targets();
+    assert_eq!(targets.last(), Some(&expected_target), "{targets:?}");
+    assert!(targets.iter().all(|t| *t <= expected_target), "{targets:?}");
+    assert_eq!(*ctx.next_input_block_n.lock().unwrap(), expected_target + 1);
+}
+
+#[rstest]
+#[tokio::test]
+/// The pipeline is given the probe height, so that it can tell whether it is syncing the chain tip.
+async fn test_pipeline_receives_probe_height() {
+    let ctx = TestContext::new();
+    let mut sync = ctx.controller(5, SyncControllerConfig::default().stop_on_sync(true), false);
+    tokio::time::timeout(Duration::from_secs(10), sync.run(ServiceContext::default())).await.unwrap().unwrap();
+
+    assert!(ctx.log.lock().unwrap().contains(&Event::PipelineRun { target: 5, probe_height: Some(5) }));
+}
+
+#[rstest]
+#[tokio::test]
+/// The pending block task must not run while the pipeline has blocks to import: it runs before the probe returned
+/// anything, and once the pipeline has caught up with the probe.
+async fn test_pending_block_only_when_pipeline_cannot_run() {
+    let ctx = TestContext::new();
+    let mut sync = ctx.controller(5, SyncControllerConfig::default().stop_on_sync(true), true);
+    tokio::time::timeout(Duration::from_secs(10), sync.run(ServiceContext::default())).await.unwrap().unwrap();
+
+    let log = ctx.log.lock().unwrap().clone();
+    let pending_at: Vec<_> = log
+        .iter()
+        .filter_map(|ev| match ev {
+            Event::PendingBlock { next_input_block_n } => Some(*next_input_block_n),
+            _ => None,
+        })
+        .collect();
+    assert!(pending_at.contains(&6), "{log:?}");
+    assert!(pending_at.iter().all(|n| *n == 0 || *n == 6), "{log:?}");
+    assert_eq!(ctx.pipeline_targets(), vec![5; 6]);
+}
+
+#[rstest]
+#[tokio::test]
+/// With the pending block disabled in the config, the task is never run.
+async fn test_no_pending_block() {
+    let ctx = TestContext::new();
+    let mut sync = ctx.controller(5, SyncControllerConfig::default().stop_on_sync(true).no_pending_block(true), true);
+    tokio::time::timeout(Duration::from_secs(10), sync.run(ServiceContext::default())).await.unwrap().unwrap();
+
+    assert!(!ctx.log.lock().unwrap().iter().any(|ev| matches!(ev, Event::PendingBlock { .. })));
+}
+
+#[rstest]
+#[tokio::test(start_paused = true)]
+/// A probe returning immediately is not called again before its wait duration has elapsed.
+async fn test_probe_wait_delay() {
+    let log = EventLog::default();
+    let mut probe = mock_probe(5, log.clone(), Duration::from_secs(2));
+
+    let start = tokio::time::Instant::now();
+    probe.run().await.unwrap();
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert_eq!(probe.last_val().map(|v| v.block_number), Some(5));
+
+    probe.run().await.unwrap();
+    assert!(start.elapsed() >= Duration::from_secs(2));
+    probe.run().await.unwrap();
+    assert!(start.elapsed() >= Duration::from_secs(4));
+    assert_eq!(log.lock().unwrap().len(), 3);
+}
+
+#[rstest]
+#[tokio::test]
+/// The number of blocks left is counted from the latest imported block, up to the sync target.
+async fn test_sync_phase() {
+    let ctx = TestContext::new();
+    // Each controller starts with a probe that has already found the chain head.
+    let controller = |probe_block_n: u64| {
+        let ctx = &ctx;
+        async move {
+            let (sender, phase) = tokio::sync::watch::channel(None);
+            let mut probe = mock_probe(probe_block_n, ctx.log.clone(), Duration::ZERO);
+            probe.run().await.unwrap();
+            (ctx.controller_with_probes(vec![probe], SyncControllerConfig::default().sync_phase_sender(sender)), phase)
+        }
+    };
+
+    // The probe has not run yet, there is no target.
+    let (sender, phase) = tokio::sync::watch::channel(None);
+    let mut sync = ctx.controller(5, SyncControllerConfig::default().sync_phase_sender(sender), false);
+    sync.update_sync_phase();
+    assert_eq!(*phase.borrow(), None);
+
+    let (mut sync, phase) = controller(5).await;
+    sync.update_sync_phase();
+    assert_eq!(*phase.borrow(), Some(SyncPhase::CatchingUp { behind: 6 }));
+
+    *ctx.next_input_block_n.lock().unwrap() = 6;
+    sync.update_sync_phase();
+    assert_eq!(*phase.borrow(), Some(SyncPhase::AtTip));
+
+    // The chain moved on.
+    let (mut sync, phase) = controller(8).await;
+    sync.update_sync_phase();
+    assert_eq!(*phase.borrow(), Some(SyncPhase::CatchingUp { behind: 3 }));
+}
+
+#[rstest]
+#[tokio::test]
+/// Once the pipeline has imported every block up to the probe height, the node is at the tip.
+async fn test_sync_phase_at_tip_once_synced() {
+    let ctx = TestContext::new();
+    let (sender, mut phase) = tokio::sync::watch::channel(None);
+    let mut sync =
+        ctx.controller(5, SyncControllerConfig::default().stop_on_sync(true).sync_phase_sender(sender), false);
+    tokio::time::timeout(Duration::from_secs(10), sync.run(ServiceContext::default())).await.unwrap().unwrap();
+
+    assert!(phase.has_changed().unwrap());
+    assert_eq!(*phase.borrow_and_update(), Some(SyncPhase::AtTip));
+}
+
+#[rstest]
+#[tokio::test]
+/// The sync target is the highest block known by any of the probes.
+async fn test_highest_probe_is_target() {
+    let ctx = TestContext::new();
+    let probes = vec![
+        mock_probe(3, ctx.log.clone(), Duration::from_millis(10)),
+        mock_probe(5, ctx.log.clone(), Duration::from_millis(10)),
+    ];
+    let mut sync = ctx.controller_with_probes(probes, SyncControllerConfig::default().stop_on_sync(true));
+    tokio::time::timeout(Duration::from_secs(10), sync.run(ServiceContext::default())).await.unwrap().unwrap();
+
+    assert_eq!(ctx.pipeline_targets().last(), Some(&5));
+    assert_eq!(*ctx.next_input_block_n.lock().unwrap(), 6);
+}
+
+#[rstest]
+#[tokio::test]
+/// A failing probe does not stop the sync while another probe keeps working.
+async fn test_failing_probe_does_not_stop_others() {
+    let ctx = TestContext::new();
+    let probes = vec![failing_probe(ctx.log.clone()), mock_probe(5, ctx.log.clone(), Duration::from_millis(10))];
+    let mut sync = ctx.controller_with_probes(probes, SyncControllerConfig::default().stop_on_sync(true));
+    tokio::time::timeout(Duration::from_secs(10), sync.run(ServiceContext::default())).await.unwrap().unwrap();
+
+    assert_eq!(ctx.pipeline_targets().last(), Some(&5));
+    assert_eq!(*ctx.next_input_block_n.lock().unwrap(), 6);
+}
+
+#[rstest]
+#[tokio::test]
+/// With every probe failing before any of them found a block, there is nothing to sync towards.
+async fn test_all_probes_failing() {
+    let ctx = TestContext::new();
+    let probes = vec![failing_probe(ctx.log.clone()), failing_probe(ctx.log.clone())];
+    let mut sync = ctx.controller_with_probes(probes, SyncControllerConfig::default().stop_on_sync(true));
+    let res = tokio::time::timeout(Duration::from_secs(10), sync.run(ServiceContext::default())).await.unwrap();
+
+    assert!(res.is_err());
+    assert!(ctx.pipeline_targets().is_empty());
+}
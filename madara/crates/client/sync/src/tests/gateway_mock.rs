@@ -4,6 +4,8 @@ use mp_state_update::DeclaredClassItem;
 use rstest::*;
 use serde_json::{json, Value};
 use starknet_core::types::Felt;
+use std::ops::RangeInclusive;
+use std::path::Path;
 use std::sync::Arc;
 
 pub struct GatewayMock {
@@ -44,6 +46,28 @@ impl GatewayMock {
         });
     }
 
+    /// Serves blocks exported with `MadaraCmd::export_blocks`, along with the classes they declare.
+    pub fn mock_blocks_from_dir(&self, dir: &Path, name: &str, range: RangeInclusive<u64>) {
+        for block_n in range {
+            let block = std::fs::read_to_string(dir.join(format!("{name}.block_{block_n}.json"))).unwrap();
+            let value: Value = serde_json::from_str(&block).unwrap();
+            let state_diff = &value["state_update"]["state_diff"];
+            let legacy =
+                state_diff["old_declared_contracts"].as_array().into_iter().flatten().filter_map(|v| v.as_str());
+            let sierra = state_diff["declared_classes"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|v| v["class_hash"].as_str());
+            for (i, class_hash) in legacy.chain(sierra).enumerate() {
+                let class =
+                    std::fs::read_to_string(dir.join(format!("{name}.block_{block_n}_class_{i}.json"))).unwrap();
+                self.mock_class_from_json(class_hash, class);
+            }
+            self.mock_block_from_json(block_n, block);
+        }
+    }
+
     pub fn mock_header_latest(&self, block_number: u64, hash: Felt) -> Mock {
         self.mock_server.mock(|when, then| {
             when.method("GET")
@@ -57,6 +81,16 @@ impl GatewayMock {
         })
     }
 
+    pub fn mock_header_latest_unavailable(&self) -> Mock {
+        self.mock_server.mock(|when, then| {
+            when.method("GET")
+                .path_contains("get_block")
+                .query_param("headerOnly", "true")
+                .query_param("blockNumber", "latest");
+            then.status(500).body("Internal Server Error");
+        })
+    }
+
     pub fn mock_block(&self, block_number: u64, hash: Felt, parent_hash: Felt) {
         self.mock_block_with_declared_class(block_number, hash, parent_hash, None);
     }
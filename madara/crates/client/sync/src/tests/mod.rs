@@ -1,5 +1,6 @@
 #![cfg(test)]
 
+mod controller;
 mod gateway_mock;
 mod pipeline;
 mod realistic;
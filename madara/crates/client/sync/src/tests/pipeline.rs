@@ -463,3 +463,71 @@ async fn test_pending_declared_class(mut ctx: TestContext) {
         .unwrap()
         .is_some());
 }
+
+#[rstest]
+#[tokio::test]
+/// The gateway probe keeps the last known header while the gateway is unavailable, instead of stopping the sync.
+async fn test_probe_survives_gateway_errors(mut ctx: TestContext) {
+    ctx.gateway_mock.mock_block(0, felt!("0x10"), felt!("0x0"));
+    ctx.gateway_mock.mock_block(1, felt!("0x11"), felt!("0x10"));
+    ctx.gateway_mock.mock_block(2, felt!("0x12"), felt!("0x11"));
+    let mut latest_mock = ctx.gateway_mock.mock_header_latest(1, felt!("0x11"));
+
+    let mut sync = crate::gateway::forward_sync(
+        ctx.backend.clone(),
+        ctx.importer,
+        ctx.gateway_mock.client(),
+        SyncControllerConfig::default().service_state_sender(ctx.service_state_sender).no_pending_block(true),
+        ForwardSyncConfig::default(),
+    );
+
+    let _task = AbortOnDrop::spawn(async move { sync.run(ServiceContext::default()).await.unwrap() });
+
+    assert_eq!(ctx.service_state_recv.recv().await.unwrap(), ServiceEvent::Starting);
+    assert_eq!(ctx.service_state_recv.recv().await.unwrap(), ServiceEvent::Idle);
+    assert_eq!(ctx.service_state_recv.recv().await.unwrap(), ServiceEvent::SyncingTo { target: 1 });
+    assert_eq!(ctx.service_state_recv.recv().await.unwrap(), ServiceEvent::Idle);
+
+    latest_mock.delete();
+    let mut unavailable_mock = ctx.gateway_mock.mock_header_latest_unavailable();
+    // Wait for the probe to hit the failing gateway.
+    tokio::time::timeout(std::time::Duration::from_secs(10), async {
+        while unavailable_mock.hits() == 0 {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("The probe did not hit the failing gateway");
+
+    unavailable_mock.delete();
+    ctx.gateway_mock.mock_header_latest(2, felt!("0x12"));
+
+    assert_eq!(ctx.service_state_recv.recv().await.unwrap(), ServiceEvent::SyncingTo { target: 2 });
+    assert_eq!(ctx.service_state_recv.recv().await.unwrap(), ServiceEvent::Idle);
+    assert_eq!(ctx.backend.get_block_hash(&DbBlockId::Number(2)).unwrap().unwrap(), felt!("0x12"));
+}
+
+#[rstest]
+#[tokio::test]
+/// Without any known header, a gateway error is a failure: the sync must not consider itself synced with stop_on_sync.
+async fn test_probe_fails_without_known_header(ctx: TestContext) {
+    let unavailable_mock = ctx.gateway_mock.mock_header_latest_unavailable();
+
+    let mut sync = crate::gateway::forward_sync(
+        ctx.backend.clone(),
+        ctx.importer,
+        ctx.gateway_mock.client(),
+        SyncControllerConfig::default()
+            .service_state_sender(ctx.service_state_sender)
+            .no_pending_block(true)
+            .stop_on_sync(true),
+        ForwardSyncConfig::default(),
+    );
+
+    let res = tokio::time::timeout(std::time::Duration::from_secs(10), sync.run(ServiceContext::default()))
+        .await
+        .expect("The sync should stop on the first probe error");
+    assert!(res.is_err());
+    assert!(unavailable_mock.hits() > 0);
+    assert_eq!(ctx.backend.get_latest_block_n().unwrap(), None);
+}
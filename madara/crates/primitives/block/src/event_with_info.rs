@@ -71,48 +71,40 @@ pub fn drain_block_events(block: MadaraMaybePendingBlock) -> impl Iterator<Item
     })
 }
 
-/// Filters events based on the provided address and keys.
+/// Filter on the emitting contract and the keys of an event, shared by `starknet_getEvents` and
+/// `starknet_subscribeEvents`.
 ///
-/// This function checks if an event matches the given address and keys.
-/// If an address is provided, the event must originate from that address.
-/// The event's keys must match the provided keys pattern.
-///
-/// # Arguments
-///
-/// * `event` - A reference to the event to be filtered.
-/// * `address` - An optional address that the event must originate from.
-/// * `keys` - An optional slice of key patterns that the event's keys must match.
-///
-/// # Returns
-///
-/// * `true` if the event matches the address and keys pattern.
-/// * `false` otherwise.
-#[inline]
-pub fn event_match_filter(event: &mp_receipt::Event, address: Option<&Felt>, keys: Option<&[Vec<Felt>]>) -> bool {
-    // Check if the event's address matches the provided address, if any.
-    if let Some(addr) = address {
-        if addr != &event.from_address {
-            return false;
-        }
+/// Keys are matched position by position: the pattern at index `i` lists the accepted values for the `i`-th key of the
+/// event, and an empty pattern accepts any value. An event must have at least as many keys as there are patterns.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    /// The address the event must originate from, or any address if `None`.
+    pub from_address: Option<Felt>,
+    /// Accepted values for each key position, or any keys if `None`.
+    pub keys: Option<Vec<Vec<Felt>>>,
+}
+
+impl EventFilter {
+    pub fn new(from_address: Option<Felt>, keys: Option<Vec<Vec<Felt>>>) -> Self {
+        Self { from_address, keys }
+    }
+
+    /// Total number of keys in all the patterns of this filter.
+    pub fn keys_len(&self) -> usize {
+        self.keys.iter().flatten().map(Vec::len).sum()
     }
 
-    // If keys are not provided, return true.
-    if let Some(keys) = keys {
-        // Check if the number of keys in the event matches the number of provided key patterns.
-        if keys.len() > event.keys.len() {
+    /// Returns true if the event matches the address and keys pattern of this filter.
+    #[inline]
+    pub fn matches(&self, event: &mp_receipt::Event) -> bool {
+        if self.from_address.is_some_and(|addr| addr != event.from_address) {
             return false;
         }
 
-        // Check if each key in the event matches the corresponding key pattern.
-        // Use iterators to traverse both keys and event.event_content.keys simultaneously.
-        for (pattern, key) in keys.iter().zip(event.keys.iter()) {
-            if !pattern.is_empty() && !pattern.contains(key) {
-                return false;
-            }
-        }
+        let Some(keys) = &self.keys else { return true };
+        keys.len() <= event.keys.len()
+            && keys.iter().zip(&event.keys).all(|(pattern, key)| pattern.is_empty() || pattern.contains(key))
     }
-
-    true
 }
 
 #[cfg(test)]
@@ -157,7 +149,7 @@ mod tests {
 
     #[rstest]
     fn test_address_and_keys_match(base_event: Event, matching_address: Felt, matching_keys: Vec<Vec<Felt>>) {
-        assert!(event_match_filter(&base_event, Some(&matching_address), Some(&matching_keys)));
+        assert!(EventFilter::new(Some(matching_address), Some(matching_keys)).matches(&base_event));
     }
 
     #[rstest]
@@ -166,27 +158,27 @@ mod tests {
         matching_address: Felt,
         matching_keys_empty: Vec<Vec<Felt>>,
     ) {
-        assert!(event_match_filter(&base_event, Some(&matching_address), Some(&matching_keys_empty)));
+        assert!(EventFilter::new(Some(matching_address), Some(matching_keys_empty)).matches(&base_event));
     }
 
     #[rstest]
     fn test_address_does_not_match(base_event: Event, non_matching_address: Felt, matching_keys: Vec<Vec<Felt>>) {
-        assert!(!event_match_filter(&base_event, Some(&non_matching_address), Some(&matching_keys)));
+        assert!(!EventFilter::new(Some(non_matching_address), Some(matching_keys)).matches(&base_event));
     }
 
     #[rstest]
     fn test_keys_do_not_match(base_event: Event, matching_address: Felt, non_matching_keys: Vec<Vec<Felt>>) {
-        assert!(!event_match_filter(&base_event, Some(&matching_address), Some(&non_matching_keys)));
+        assert!(!EventFilter::new(Some(matching_address), Some(non_matching_keys)).matches(&base_event));
     }
 
     #[rstest]
     fn test_no_address_provided(base_event: Event, matching_keys: Vec<Vec<Felt>>) {
-        assert!(event_match_filter(&base_event, None, Some(&matching_keys)));
+        assert!(EventFilter::new(None, Some(matching_keys)).matches(&base_event));
     }
 
     #[rstest]
     fn test_no_keys_provided(base_event: Event, matching_address: Felt) {
-        assert!(event_match_filter(&base_event, Some(&matching_address), None));
+        assert!(EventFilter::new(Some(matching_address), None).matches(&base_event));
     }
 
     #[rstest]
@@ -196,10 +188,50 @@ mod tests {
             vec![Felt::from_hex_unchecked("0x1"), Felt::from_hex_unchecked("0x2")],
             vec![Felt::from_hex_unchecked("0x2")],
         ];
-        assert!(event_match_filter(&base_event, Some(&matching_address), Some(&keys)));
+        assert!(EventFilter::new(Some(matching_address), Some(keys)).matches(&base_event));
 
         // [_, 0x3 | 0x2]
         let keys = vec![vec![], vec![Felt::from_hex_unchecked("0x3"), Felt::from_hex_unchecked("0x2")]];
-        assert!(event_match_filter(&base_event, Some(&matching_address), Some(&keys)));
+        assert!(EventFilter::new(Some(matching_address), Some(keys)).matches(&base_event));
+    }
+
+    #[rstest]
+    fn test_address_only(base_event: Event, matching_address: Felt, non_matching_address: Felt) {
+        assert!(EventFilter::new(Some(matching_address), None).matches(&base_event));
+        assert!(EventFilter::new(Some(matching_address), Some(vec![])).matches(&base_event));
+        assert!(!EventFilter::new(Some(non_matching_address), None).matches(&base_event));
+        assert!(EventFilter::default().matches(&base_event));
+    }
+
+    #[rstest]
+    fn test_wildcard_positions(base_event: Event) {
+        // [0x1, _]
+        let keys = vec![vec![Felt::from_hex_unchecked("0x1")], vec![]];
+        assert!(EventFilter::new(None, Some(keys)).matches(&base_event));
+
+        // [_, 0x1]: a wildcard does not make the following positions match anything.
+        let keys = vec![vec![], vec![Felt::from_hex_unchecked("0x1")]];
+        assert!(!EventFilter::new(None, Some(keys)).matches(&base_event));
+
+        // [0x1]: patterns are a prefix of the event keys.
+        let keys = vec![vec![Felt::from_hex_unchecked("0x1")]];
+        assert!(EventFilter::new(None, Some(keys)).matches(&base_event));
+
+        // [0x2]: the first pattern applies to the first key only.
+        let keys = vec![vec![Felt::from_hex_unchecked("0x2")]];
+        assert!(!EventFilter::new(None, Some(keys)).matches(&base_event));
+    }
+
+    #[rstest]
+    fn test_more_patterns_than_keys(base_event: Event) {
+        // [_, _, _]: the event only has two keys.
+        assert!(!EventFilter::new(None, Some(vec![vec![], vec![], vec![]])).matches(&base_event));
+    }
+
+    #[rstest]
+    fn test_keys_len() {
+        assert_eq!(EventFilter::default().keys_len(), 0);
+        let keys = vec![vec![Felt::ONE, Felt::TWO], vec![], vec![Felt::THREE]];
+        assert_eq!(EventFilter::new(None, Some(keys)).keys_len(), 3);
     }
 }
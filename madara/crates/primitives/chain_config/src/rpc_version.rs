@@ -1,8 +1,10 @@
 use std::hash::Hash;
 use std::str::FromStr;
 
-const SUPPORTED_RPC_VERSIONS: [RpcVersion; 3] =
-    [RpcVersion::RPC_VERSION_0_7_1, RpcVersion::RPC_VERSION_0_8_0, RpcVersion::RPC_VERSION_ADMIN_0_1_0];
+/// Every rpc version served by the user rpc endpoint.
+pub const SUPPORTED_RPC_VERSIONS_USER: &[RpcVersion] = &[RpcVersion::RPC_VERSION_0_7_1, RpcVersion::RPC_VERSION_0_8_0];
+/// Every rpc version served by the admin rpc endpoint.
+pub const SUPPORTED_RPC_VERSIONS_ADMIN: &[RpcVersion] = &[RpcVersion::RPC_VERSION_ADMIN_0_1_0];
 
 #[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize, Hash)]
 pub struct RpcVersion([u8; 3]);
@@ -26,8 +28,13 @@ impl RpcVersion {
         RpcVersion([major, minor, patch])
     }
 
-    #[tracing::instrument(skip(path), fields(module = "RpcVersion"))]
-    pub fn from_request_path(path: &str, version_default: RpcVersion) -> Result<Self, RpcVersionError> {
+    /// Extracts the rpc version targeted by a request path, which must be one of `versions_supported`.
+    #[tracing::instrument(skip(path, versions_supported), fields(module = "RpcVersion"))]
+    pub fn from_request_path(
+        path: &str,
+        version_default: RpcVersion,
+        versions_supported: &[RpcVersion],
+    ) -> Result<Self, RpcVersionError> {
         tracing::debug!(target: "rpc_version", "extracting rpc version from request: {path}");
 
         let path = path.to_ascii_lowercase();
@@ -45,7 +52,7 @@ impl RpcVersion {
             ["rpc", version_str] if version_str.starts_with('v') => {
                 let version_str = &version_str[1..]; // strip "v"
                 match RpcVersion::from_str(version_str) {
-                    Ok(version) if SUPPORTED_RPC_VERSIONS.contains(&version) => {
+                    Ok(version) if versions_supported.contains(&version) => {
                         tracing::debug!(target: "rpc_version", "Found supported version: {version}");
                         Ok(version)
                     }
@@ -170,19 +177,23 @@ mod tests {
     #[test]
     fn test_from_request_path_valid() {
         assert_eq!(
-            RpcVersion::from_request_path("/rpc/v0_7_1/", RpcVersion::RPC_VERSION_LATEST).unwrap(),
+            RpcVersion::from_request_path("/rpc/v0_7_1/", RpcVersion::RPC_VERSION_LATEST, SUPPORTED_RPC_VERSIONS_USER)
+                .unwrap(),
             RpcVersion::RPC_VERSION_0_7_1
         );
         assert_eq!(
-            RpcVersion::from_request_path("/rpc/v0_7_1", RpcVersion::RPC_VERSION_LATEST).unwrap(),
+            RpcVersion::from_request_path("/rpc/v0_7_1", RpcVersion::RPC_VERSION_LATEST, SUPPORTED_RPC_VERSIONS_USER)
+                .unwrap(),
             RpcVersion::RPC_VERSION_0_7_1
         );
         assert_eq!(
-            RpcVersion::from_request_path("/rpc/v0_8_0/", RpcVersion::RPC_VERSION_LATEST).unwrap(),
+            RpcVersion::from_request_path("/rpc/v0_8_0/", RpcVersion::RPC_VERSION_LATEST, SUPPORTED_RPC_VERSIONS_USER)
+                .unwrap(),
             RpcVersion::RPC_VERSION_0_8_0
         );
         assert_eq!(
-            RpcVersion::from_request_path("/rpc/v0_8_0", RpcVersion::RPC_VERSION_LATEST).unwrap(),
+            RpcVersion::from_request_path("/rpc/v0_8_0", RpcVersion::RPC_VERSION_LATEST, SUPPORTED_RPC_VERSIONS_USER)
+                .unwrap(),
             RpcVersion::RPC_VERSION_0_8_0
         );
     }
@@ -190,7 +201,7 @@ mod tests {
     #[test]
     fn test_from_request_path_empty() {
         assert_eq!(
-            RpcVersion::from_request_path("", RpcVersion::RPC_VERSION_LATEST).unwrap(),
+            RpcVersion::from_request_path("", RpcVersion::RPC_VERSION_LATEST, SUPPORTED_RPC_VERSIONS_USER).unwrap(),
             RpcVersion::RPC_VERSION_LATEST
         );
     }
@@ -198,7 +209,7 @@ mod tests {
     #[test]
     fn test_from_request_path_root() {
         assert_eq!(
-            RpcVersion::from_request_path("/", RpcVersion::RPC_VERSION_LATEST).unwrap(),
+            RpcVersion::from_request_path("/", RpcVersion::RPC_VERSION_LATEST, SUPPORTED_RPC_VERSIONS_USER).unwrap(),
             RpcVersion::RPC_VERSION_LATEST
         );
     }
@@ -206,7 +217,8 @@ mod tests {
     #[test]
     fn test_from_request_path_invalid_format() {
         assert_eq!(
-            RpcVersion::from_request_path("/invalid/path", RpcVersion::RPC_VERSION_LATEST).unwrap(),
+            RpcVersion::from_request_path("/invalid/path", RpcVersion::RPC_VERSION_LATEST, SUPPORTED_RPC_VERSIONS_USER)
+                .unwrap(),
             RpcVersion::RPC_VERSION_LATEST
         );
     }
@@ -214,7 +226,23 @@ mod tests {
     #[test]
     fn test_from_request_path_unsupported_version() {
         assert_eq!(
-            RpcVersion::from_request_path("/rpc/v9_9_9", RpcVersion::RPC_VERSION_LATEST),
+            RpcVersion::from_request_path("/rpc/v9_9_9", RpcVersion::RPC_VERSION_LATEST, SUPPORTED_RPC_VERSIONS_USER),
+            Err(RpcVersionError::UnsupportedVersion)
+        );
+    }
+
+    #[test]
+    fn test_from_request_path_version_of_other_endpoint() {
+        assert_eq!(
+            RpcVersion::from_request_path("/rpc/v0_1_0", RpcVersion::RPC_VERSION_LATEST, SUPPORTED_RPC_VERSIONS_USER),
+            Err(RpcVersionError::UnsupportedVersion)
+        );
+        assert_eq!(
+            RpcVersion::from_request_path(
+                "/rpc/v0_8_0",
+                RpcVersion::RPC_VERSION_LATEST_ADMIN,
+                SUPPORTED_RPC_VERSIONS_ADMIN
+            ),
             Err(RpcVersionError::UnsupportedVersion)
         );
     }
@@ -222,7 +250,7 @@ mod tests {
     #[test]
     fn test_from_request_path_invalid_version() {
         assert_eq!(
-            RpcVersion::from_request_path("/rpc/vx_y_z", RpcVersion::RPC_VERSION_LATEST),
+            RpcVersion::from_request_path("/rpc/vx_y_z", RpcVersion::RPC_VERSION_LATEST, SUPPORTED_RPC_VERSIONS_USER),
             Err(RpcVersionError::InvalidVersion)
         );
     }
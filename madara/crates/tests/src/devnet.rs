@@ -2,7 +2,7 @@ use crate::{wait_for_cond, MadaraCmdBuilder};
 use rstest::rstest;
 use starknet::accounts::{Account, ExecutionEncoding, SingleOwnerAccount};
 use starknet::signers::{LocalWallet, SigningKey};
-use starknet_core::types::{BlockId, BlockTag, Call, Felt, ReceiptBlock};
+use starknet_core::types::{BlockId, BlockTag, Call, Felt, ReceiptBlock, TransactionFinalityStatus};
 use starknet_core::utils::starknet_keccak;
 use starknet_providers::Provider;
 use std::time::Duration;
@@ -82,16 +82,11 @@ async fn madara_devnet_add_transaction() {
         .await
         .unwrap();
 
-    wait_for_cond(
-        || async {
-            let receipt = node.json_rpc().get_transaction_receipt(res.transaction_hash).await?;
-            assert!(receipt.block.is_block());
-            Ok(())
-        },
-        Duration::from_millis(500),
-        60,
-    )
-    .await;
+    node.wait_for_acceptance(res.transaction_hash, TransactionFinalityStatus::AcceptedOnL2, Duration::from_secs(30))
+        .await
+        .unwrap();
+    // No pending block is produced: the transaction is in a closed block.
+    assert!(node.json_rpc().get_transaction_receipt(res.transaction_hash).await.unwrap().block.is_block());
 
     tokio::time::sleep(Duration::from_secs(2)).await;
 
@@ -105,16 +100,11 @@ async fn madara_devnet_add_transaction() {
         .await
         .unwrap();
 
-    wait_for_cond(
-        || async {
-            let receipt = node.json_rpc().get_transaction_receipt(res.transaction_hash).await?;
-            assert!(receipt.block.is_block());
-            Ok(())
-        },
-        Duration::from_millis(500),
-        60,
-    )
-    .await;
+    node.wait_for_acceptance(res.transaction_hash, TransactionFinalityStatus::AcceptedOnL2, Duration::from_secs(30))
+        .await
+        .unwrap();
+    // No pending block is produced: the transaction is in a closed block.
+    assert!(node.json_rpc().get_transaction_receipt(res.transaction_hash).await.unwrap().block.is_block());
 }
 
 #[rstest]
@@ -183,4 +173,5 @@ async fn madara_devnet_mempool_saving() {
         60,
     )
     .await;
+    assert!(node.is_transaction_pending(res.transaction_hash).await.unwrap());
 }
@@ -4,13 +4,18 @@
 mod devnet;
 mod rpc;
 mod storage_proof;
+mod subscription;
 mod transaction_flow;
 
-use anyhow::bail;
+use anyhow::{bail, Context};
 use rstest::rstest;
-use starknet_core::types::Felt;
+use starknet_core::types::{
+    BlockId, BlockTag, BroadcastedDeclareTransaction, BroadcastedDeployAccountTransaction,
+    BroadcastedInvokeTransaction, EmittedEvent, EventFilter, Felt, MaybePendingBlockWithTxHashes, StarknetError,
+    TransactionExecutionStatus, TransactionFinalityStatus, TransactionStatus,
+};
 use starknet_providers::{jsonrpc::HttpTransport, JsonRpcClient, Url};
-use starknet_providers::{Provider, SequencerGatewayProvider};
+use starknet_providers::{Provider, ProviderError, SequencerGatewayProvider};
 use std::io::{BufRead, BufReader};
 use std::process::Stdio;
 use std::sync::mpsc::TryRecvError;
@@ -21,8 +26,9 @@ use std::{
     collections::HashMap,
     env,
     future::Future,
+    ops::RangeInclusive,
     path::{Path, PathBuf},
-    process::{Child, Command, Output},
+    process::{Child, Command, ExitStatus, Output},
     time::Duration,
 };
 use tempfile::TempDir;
@@ -49,11 +55,83 @@ async fn wait_for_cond<F: Future<Output = Result<R, anyhow::Error>>, R>(
     }
 }
 
+/// Why a transaction did not reach the status expected by [`MadaraCmd::wait_for_acceptance`].
+#[derive(Debug)]
+pub enum TransactionFailure {
+    Rejected { tx_hash: Felt },
+    Reverted { tx_hash: Felt },
+    Timeout { tx_hash: Felt, status: Option<TransactionStatus> },
+}
+
+impl std::fmt::Display for TransactionFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Rejected { tx_hash } => write!(f, "Transaction {tx_hash:#x} was rejected"),
+            Self::Reverted { tx_hash } => write!(f, "Transaction {tx_hash:#x} was reverted"),
+            Self::Timeout { tx_hash, status } => {
+                write!(f, "Transaction {tx_hash:#x} did not reach the expected status, last status: {status:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TransactionFailure {}
+
+/// Why [`MadaraCmd::dump_database`] could not make a snapshot of the database.
+#[derive(Debug)]
+pub enum SnapshotFailure {
+    /// The node did not create the database checkpoint.
+    CheckpointNotAcknowledged(String),
+}
+
+impl std::fmt::Display for SnapshotFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CheckpointNotAcknowledged(reason) => {
+                write!(f, "Database checkpoint was not acknowledged: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotFailure {}
+
+/// Latest block of a node, see [`MadaraCmd::head`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockHead {
+    pub number: u64,
+    pub hash: Felt,
+    pub state_root: Felt,
+}
+
+/// How a node process ended after being asked to stop.
+#[derive(Debug)]
+pub enum StopOutcome {
+    /// The process exited on its own within the shutdown grace period.
+    Exited(ExitStatus),
+    /// The process did not exit within the shutdown grace period and was killed.
+    Killed,
+}
+
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(5);
+const GET_EVENTS_CHUNK_SIZE: u64 = 100;
+
+/// Hashes of the legacy and sierra classes declared in a `get_state_update?includeBlock=true` response, in the order
+/// used to number the exported class files.
+fn declared_class_hashes(block: &serde_json::Value) -> Vec<String> {
+    let state_diff = &block["state_update"]["state_diff"];
+    let legacy = state_diff["old_declared_contracts"].as_array().into_iter().flatten().filter_map(|v| v.as_str());
+    let sierra =
+        state_diff["declared_classes"].as_array().into_iter().flatten().filter_map(|v| v["class_hash"].as_str());
+    legacy.chain(sierra).map(String::from).collect()
+}
+
 pub struct MadaraCmd {
     process: Option<Child>,
     ready: bool,
     json_rpc: Option<JsonRpcClient<HttpTransport>>,
     rpc_url: Option<Url>,
+    admin_rpc_url: Option<Url>,
     gateway_root_url: Option<Url>,
     tempdir: Arc<TempDir>,
     label: String,
@@ -90,10 +168,189 @@ impl MadaraCmd {
         format!("{}/feeder_gateway", self.gateway_root_url.as_ref().unwrap())
     }
 
+    /// Websocket url for the given rpc version, for example `v0_8_0`.
+    pub fn ws_url(&self, version: &str) -> String {
+        let mut url = self.rpc_url.clone().unwrap();
+        url.set_scheme("ws").unwrap();
+        url.join(&format!("rpc/{version}/")).unwrap().to_string()
+    }
+
+    /// All the events matching `filter`, following continuation tokens until every page has been fetched.
+    pub async fn get_events(&self, filter: EventFilter) -> anyhow::Result<Vec<EmittedEvent>> {
+        let mut events = vec![];
+        let mut continuation_token = None;
+        loop {
+            let page = self.json_rpc().get_events(filter.clone(), continuation_token, GET_EVENTS_CHUNK_SIZE).await?;
+            events.extend(page.events);
+            match page.continuation_token {
+                Some(token) if !token.is_empty() => continuation_token = Some(token),
+                _ => break Ok(events),
+            }
+        }
+    }
+
+    /// Hashes of the transactions in the pending block, in execution order. Fails if the node returns a closed block
+    /// instead, which happens when it does not produce or sync a pending block.
+    pub async fn pending_transactions(&self) -> anyhow::Result<Vec<Felt>> {
+        match self.json_rpc().get_block_with_tx_hashes(BlockId::Tag(BlockTag::Pending)).await? {
+            MaybePendingBlockWithTxHashes::PendingBlock(block) => Ok(block.transactions),
+            MaybePendingBlockWithTxHashes::Block(block) => {
+                bail!("No pending block: got block #{} instead", block.block_number)
+            }
+        }
+    }
+
+    pub async fn is_transaction_pending(&self, tx_hash: Felt) -> anyhow::Result<bool> {
+        Ok(self.pending_transactions().await?.contains(&tx_hash))
+    }
+
+    /// Number, hash and state root of the latest block, or `None` when the node has no block yet.
+    pub async fn head(&self) -> anyhow::Result<Option<BlockHead>> {
+        match self.json_rpc().get_block_with_tx_hashes(BlockId::Tag(BlockTag::Latest)).await {
+            Ok(MaybePendingBlockWithTxHashes::Block(block)) => {
+                Ok(Some(BlockHead { number: block.block_number, hash: block.block_hash, state_root: block.new_root }))
+            }
+            Ok(MaybePendingBlockWithTxHashes::PendingBlock(_)) => bail!("Latest block is pending"),
+            Err(ProviderError::StarknetError(StarknetError::BlockNotFound)) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Submits the transaction and returns its hash, without waiting for it to be included in a block.
+    pub async fn submit_invoke(&self, tx: BroadcastedInvokeTransaction) -> anyhow::Result<Felt> {
+        Ok(self.json_rpc().add_invoke_transaction(tx).await?.transaction_hash)
+    }
+
+    /// Submits the transaction and returns its hash, without waiting for it to be included in a block.
+    pub async fn submit_declare(&self, tx: BroadcastedDeclareTransaction) -> anyhow::Result<Felt> {
+        Ok(self.json_rpc().add_declare_transaction(tx).await?.transaction_hash)
+    }
+
+    /// Submits the transaction and returns its hash, without waiting for it to be included in a block.
+    pub async fn submit_deploy_account(&self, tx: BroadcastedDeployAccountTransaction) -> anyhow::Result<Felt> {
+        Ok(self.json_rpc().add_deploy_account_transaction(tx).await?.transaction_hash)
+    }
+
+    /// Polls the status of the transaction until it reaches `target`. A rejected or reverted transaction fails
+    /// right away with the matching [`TransactionFailure`], which can be downcast from the returned error.
+    pub async fn wait_for_acceptance(
+        &self,
+        tx_hash: Felt,
+        target: TransactionFinalityStatus,
+        timeout: Duration,
+    ) -> anyhow::Result<()> {
+        let start = Instant::now();
+        let mut status = None;
+        while start.elapsed() < timeout {
+            // The transaction is not known until the node has received it.
+            if let Ok(new_status) = self.json_rpc().get_transaction_status(tx_hash).await {
+                let execution_status = match &new_status {
+                    TransactionStatus::Received => None,
+                    TransactionStatus::Rejected => return Err(TransactionFailure::Rejected { tx_hash }.into()),
+                    TransactionStatus::AcceptedOnL2(execution_status) => {
+                        Some((TransactionFinalityStatus::AcceptedOnL2, execution_status))
+                    }
+                    TransactionStatus::AcceptedOnL1(execution_status) => {
+                        Some((TransactionFinalityStatus::AcceptedOnL1, execution_status))
+                    }
+                };
+                if let Some((finality_status, execution_status)) = execution_status {
+                    if *execution_status == TransactionExecutionStatus::Reverted {
+                        return Err(TransactionFailure::Reverted { tx_hash }.into());
+                    }
+                    if finality_status == target || finality_status == TransactionFinalityStatus::AcceptedOnL1 {
+                        return Ok(());
+                    }
+                }
+                status = Some(new_status);
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        Err(TransactionFailure::Timeout { tx_hash, status }.into())
+    }
+
+    /// Exports blocks from the feeder gateway into `out`, in the format of the fixtures in `crates/resources`: the
+    /// state update with its block is written to `{name}.block_{n}.json`, and every class declared in that block to
+    /// `{name}.block_{n}_class_{i}.json`. Fails if the range extends past the latest block of the node.
+    pub async fn export_blocks(&self, range: RangeInclusive<u64>, out: &Path, name: &str) -> anyhow::Result<()> {
+        let latest: serde_json::Value =
+            reqwest::get(format!("{}/get_block?blockNumber=latest", self.feeder_gateway_url()))
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+        let latest_block_n = latest["block_number"].as_u64().context("Latest block has no block number")?;
+        if *range.end() > latest_block_n {
+            bail!("Cannot export blocks {range:?}: the latest block is #{latest_block_n}");
+        }
+
+        for block_n in range {
+            let block: serde_json::Value = reqwest::get(format!(
+                "{}/get_state_update?blockNumber={block_n}&includeBlock=true",
+                self.feeder_gateway_url()
+            ))
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+            std::fs::write(out.join(format!("{name}.block_{block_n}.json")), serde_json::to_vec(&block)?)?;
+
+            for (i, class_hash) in declared_class_hashes(&block).iter().enumerate() {
+                let class =
+                    reqwest::get(format!("{}/get_class_by_hash?classHash={class_hash}", self.feeder_gateway_url()))
+                        .await?
+                        .error_for_status()?
+                        .bytes()
+                        .await?;
+                std::fs::write(out.join(format!("{name}.block_{block_n}_class_{i}.json")), class)?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn db_dir(&self) -> &Path {
         self.tempdir.path()
     }
 
+    /// Makes the running node create a RocksDB checkpoint of its database in `out` through the admin RPC. `out` must
+    /// be an existing directory without a database, and can then be used as the base path of another node. Returns
+    /// the latest block in the dump.
+    ///
+    /// The admin RPC has to be enabled with [`MadaraCmdBuilder::enable_admin_rpc`]. The checkpoint is consistent even
+    /// while the node imports blocks, in which case it may contain blocks after the returned one.
+    pub async fn dump_database(&self, out: &Path) -> Result<Option<u64>, SnapshotFailure> {
+        let admin_rpc_url = self
+            .admin_rpc_url
+            .as_ref()
+            .ok_or_else(|| SnapshotFailure::CheckpointNotAcknowledged("the admin RPC is not enabled".into()))?;
+        let response: serde_json::Value = async {
+            reqwest::Client::new()
+                .post(admin_rpc_url.clone())
+                .json(&serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "method": "madara_checkpoint",
+                    "params": [out.join("db")],
+                    "id": 1
+                }))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+        }
+        .await
+        .map_err(|err| SnapshotFailure::CheckpointNotAcknowledged(err.to_string()))?;
+
+        if let Some(err) = response.get("error") {
+            return Err(SnapshotFailure::CheckpointNotAcknowledged(err.to_string()));
+        }
+        let block_n = response
+            .get("result")
+            .and_then(|result| serde_json::from_value(result.clone()).ok())
+            .ok_or_else(|| SnapshotFailure::CheckpointNotAcknowledged(format!("unexpected response {response}")))?;
+        Ok(block_n)
+    }
+
     pub async fn wait_for_ready(&mut self) -> &mut Self {
         let endpoint = self.rpc_url.as_ref().unwrap().join("/health").unwrap();
         wait_for_cond(
@@ -110,6 +367,23 @@ impl MadaraCmd {
         self
     }
 
+    /// The gateway accepts connections before it can serve blocks: this waits until the feeder gateway returns the
+    /// latest block.
+    pub async fn wait_for_gateway_ready(&mut self) -> &mut Self {
+        let endpoint = format!("{}/get_block?blockNumber=latest", self.feeder_gateway_url());
+        wait_for_cond(
+            || async {
+                let res = reqwest::get(&endpoint).await?.error_for_status()?;
+                res.json::<serde_json::Value>().await?;
+                anyhow::Ok(())
+            },
+            Duration::from_millis(500),
+            50,
+        )
+        .await;
+        self
+    }
+
     // TODO: replace this with `subscribeNewHeads`
     pub async fn wait_for_sync_to(&mut self, block_n: u64) -> &mut Self {
         let rpc = self.json_rpc();
@@ -140,33 +414,54 @@ impl MadaraCmd {
     }
 
     pub fn stop(&mut self) {
-        let Some(mut child) = self.process.take() else { return };
+        self.signal_and_wait("-TERM");
+    }
 
-        // Send SIGTERM signal to gracefully terminate the process
-        let termination_result = Command::new("kill").arg("-TERM").arg(child.id().to_string()).status();
+    /// Sends a SIGINT to the node, the equivalent of a Ctrl-C.
+    pub fn interrupt(&self) {
+        let Some(child) = self.process.as_ref() else { return };
+        let _ = Command::new("kill").arg("-INT").arg(child.id().to_string()).status();
+    }
+
+    /// Interrupts the node and waits for it to exit, killing it if it has not exited after the shutdown grace
+    /// period.
+    pub fn graceful_stop(&mut self) -> Option<StopOutcome> {
+        self.signal_and_wait("-INT")
+    }
+
+    fn signal_and_wait(&mut self, signal: &str) -> Option<StopOutcome> {
+        let mut child = self.process.take()?;
+
+        // Send the signal to gracefully terminate the process
+        let termination_result = Command::new("kill").arg(signal).arg(child.id().to_string()).status();
 
         // Force kill if graceful termination failed
         if termination_result.is_err() {
             let _ = child.kill();
         }
 
-        let grace_period = Duration::from_secs(5);
         let termination_start = std::time::Instant::now();
 
         // Wait for process exit or force kill after grace period
-        while let Ok(None) = child.try_wait() {
-            if termination_start.elapsed() >= grace_period {
-                let _ = child.kill();
-                break;
+        let outcome = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break StopOutcome::Exited(status),
+                Ok(None) if termination_start.elapsed() < SHUTDOWN_GRACE_PERIOD => {
+                    std::thread::sleep(Duration::from_millis(100))
+                }
+                _ => {
+                    let _ = child.kill();
+                    break StopOutcome::Killed;
+                }
             }
-            std::thread::sleep(Duration::from_millis(100));
-        }
+        };
 
         // Ensure process cleanup
         let _ = child.wait();
+        Some(outcome)
     }
 
-    pub fn hook_stdout_and_wait_for_ports(&mut self, rpc: bool, gateway: bool) {
+    pub fn hook_stdout_and_wait_for_ports(&mut self, rpc: bool, gateway: bool, admin_rpc: bool) {
         let stderr =
             self.process.as_mut().unwrap().stderr.take().expect("Could not capture stderr from Madara process");
         let pid = self.process.as_ref().unwrap().id();
@@ -179,6 +474,7 @@ impl MadaraCmd {
         thread::spawn(move || {
             let mut rpc_port = None;
             let mut gateway_port = None;
+            let mut admin_rpc_port = None;
 
             for line in reader.lines().map_while(Result::ok) {
                 fn get_port(line: &str, prefix: &str) -> Option<u16> {
@@ -196,16 +492,24 @@ impl MadaraCmd {
 
                 rpc_port = rpc_port.or(get_port(&line, "Running JSON-RPC server at "));
                 gateway_port = gateway_port.or(get_port(&line, "Gateway endpoint started at "));
+                admin_rpc_port = admin_rpc_port.or(get_port(&line, "Running JSON-RPC (Admin) server at "));
 
-                if (!rpc && rpc_port.is_some()) || (!gateway && gateway_port.is_some()) {
+                if (!rpc && rpc_port.is_some())
+                    || (!gateway && gateway_port.is_some())
+                    || (!admin_rpc && admin_rpc_port.is_some())
+                {
                     panic!(
                         "Inconsistent returned ports: expected rpc_enabled={rpc}, gateway_enabled={gateway}, \
-                        got rpc_port={rpc_port:?}, gateway_port={gateway_port:?}"
+                        admin_rpc_enabled={admin_rpc}, got rpc_port={rpc_port:?}, gateway_port={gateway_port:?}, \
+                        admin_rpc_port={admin_rpc_port:?}"
                     )
                 }
 
-                if (rpc == rpc_port.is_some()) && (gateway == gateway_port.is_some()) {
-                    let _ = tx.send((rpc_port, gateway_port));
+                if (rpc == rpc_port.is_some())
+                    && (gateway == gateway_port.is_some())
+                    && (admin_rpc == admin_rpc_port.is_some())
+                {
+                    let _ = tx.send((rpc_port, gateway_port, admin_rpc_port));
                 }
                 println!("{stdout_prefix} {line}");
             }
@@ -216,14 +520,17 @@ impl MadaraCmd {
 
         while start.elapsed() < timeout {
             match rx.try_recv() {
-                Ok((rpc_port, gateway_port)) => {
+                Ok((rpc_port, gateway_port, admin_rpc_port)) => {
                     let rpc_url = rpc_port.map(|port| Url::parse(&format!("http://127.0.0.1:{port}/")).unwrap());
+                    let admin_rpc_url =
+                        admin_rpc_port.map(|port| Url::parse(&format!("http://127.0.0.1:{port}/")).unwrap());
                     let gateway_root_url =
                         gateway_port.map(|port| Url::parse(&format!("http://127.0.0.1:{port}/")).unwrap());
 
                     let json_rpc = rpc_url.as_ref().map(|url| JsonRpcClient::new(HttpTransport::new(url.clone())));
 
                     self.rpc_url = rpc_url;
+                    self.admin_rpc_url = admin_rpc_url;
                     self.json_rpc = json_rpc;
                     self.gateway_root_url = gateway_root_url;
                     return;
@@ -245,6 +552,41 @@ impl Drop for MadaraCmd {
     }
 }
 
+/// A coherent set of arguments for a kind of node. The arguments given through [`MadaraCmdBuilder::args`] override
+/// the flags of the profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MadaraProfile {
+    /// Devnet sequencer, producing blocks without any L1.
+    Sequencer,
+    /// Read-only node syncing from the network given in the arguments, without any L1.
+    FullNode,
+    /// Full node which also keeps the history of the global tries, to serve storage proofs for past blocks.
+    ArchiveNode,
+}
+
+/// Flags selecting the mode of the node, only one of them can be given.
+const MODE_FLAGS: &[&str] = &["--sequencer", "--full", "--devnet"];
+
+impl MadaraProfile {
+    /// How many blocks back an archive node serves storage proofs for.
+    const ARCHIVE_DEPTH: &'static str = "1000000";
+
+    fn flags(self) -> &'static [(&'static str, Option<&'static str>)] {
+        match self {
+            Self::Sequencer => &[("--devnet", None), ("--no-l1-sync", None), ("--gas-price", Some("0"))],
+            Self::FullNode => &[("--full", None), ("--no-l1-sync", None), ("--gas-price", Some("0"))],
+            Self::ArchiveNode => &[
+                ("--full", None),
+                ("--no-l1-sync", None),
+                ("--gas-price", Some("0")),
+                ("--db-max-saved-trie-logs", Some(Self::ARCHIVE_DEPTH)),
+                ("--db-max-kept-snapshots", Some("1000")),
+                ("--rpc-storage-proof-max-distance", Some(Self::ARCHIVE_DEPTH)),
+            ],
+        }
+    }
+}
+
 /// Note: the builder is [`Clone`]able. When cloned, it will keep the same tempdir.
 ///
 /// This is useful for tests that need to restart the node using the same DB: they
@@ -257,7 +599,10 @@ pub struct MadaraCmdBuilder {
     env: HashMap<String, String>,
     tempdir: Arc<TempDir>,
     rpc_enabled: bool,
+    rpc_port: Option<u16>,
     gateway_enabled: bool,
+    admin_rpc_enabled: bool,
+    profile: Option<MadaraProfile>,
     label: String,
 }
 
@@ -268,7 +613,10 @@ impl Default for MadaraCmdBuilder {
             env: Default::default(),
             tempdir: Arc::new(TempDir::with_prefix("madara-test").unwrap()),
             rpc_enabled: true,
+            rpc_port: None,
             gateway_enabled: false,
+            admin_rpc_enabled: false,
+            profile: None,
             label: String::new(),
         }
     }
@@ -282,9 +630,24 @@ impl MadaraCmdBuilder {
     pub fn no_rpc(self) -> Self {
         Self { rpc_enabled: false, ..self }
     }
+    /// Serves the RPC on this port instead of one assigned by the OS, for example to restart a node on the port it
+    /// was previously assigned.
+    pub fn rpc_port(self, rpc_port: u16) -> Self {
+        Self { rpc_port: Some(rpc_port), ..self }
+    }
     pub fn enable_gateway(self) -> Self {
         Self { gateway_enabled: true, ..self }
     }
+    pub fn enable_admin_rpc(self) -> Self {
+        Self { admin_rpc_enabled: true, ..self }
+    }
+    pub fn profile(self, profile: MadaraProfile) -> Self {
+        Self { profile: Some(profile), ..self }
+    }
+
+    pub fn db_dir(&self) -> &Path {
+        self.tempdir.path()
+    }
 
     pub fn args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
         self.args = args.into_iter().map(Into::into).collect();
@@ -301,11 +664,28 @@ impl MadaraCmdBuilder {
         self
     }
 
+    /// The flags of the profile which are not overridden by the arguments, followed by the arguments.
+    fn node_args(&self) -> Vec<String> {
+        let given = |flag: &str| self.args.iter().any(|arg| arg == flag || arg.starts_with(&format!("{flag}=")));
+        let overridden =
+            |flag: &str| given(flag) || (MODE_FLAGS.contains(&flag) && MODE_FLAGS.iter().any(|f| given(f)));
+
+        self.profile
+            .map(MadaraProfile::flags)
+            .unwrap_or_default()
+            .iter()
+            .filter(|(flag, _)| !overridden(flag))
+            .flat_map(|(flag, value)| std::iter::once(*flag).chain(*value))
+            .map(String::from)
+            .chain(self.args.iter().cloned())
+            .collect()
+    }
+
     /// Also waits for the ports to be assigned.
     pub fn run(self) -> MadaraCmd {
-        let (rpc, gateway) = (self.rpc_enabled, self.gateway_enabled);
+        let (rpc, gateway, admin_rpc) = (self.rpc_enabled, self.gateway_enabled, self.admin_rpc_enabled);
         let mut cmd = self.run_no_wait();
-        cmd.hook_stdout_and_wait_for_ports(rpc, gateway);
+        cmd.hook_stdout_and_wait_for_ports(rpc, gateway, admin_rpc);
         cmd
     }
 
@@ -314,29 +694,38 @@ impl MadaraCmdBuilder {
         let target_bin = PathBuf::from(env::var("COVERAGE_BIN").expect("env COVERAGE_BIN to be set by script"));
 
         assert!(target_bin.exists(), "No binary to run: {:?}", target_bin);
+        // The process is not run from the current directory.
+        let target_bin = target_bin.canonicalize().expect("Resolving binary path");
 
         let gateway_key_args =
             env::var("GATEWAY_KEY").ok().map(|key| vec!["--gateway-key".into(), key]).unwrap_or_default();
 
-        tracing::info!("Running new madara process with args {:?}", self.args);
+        let args = self.node_args();
+        let rpc_port = self.rpc_port.unwrap_or(0).to_string(); // 0 is OS Assigned
+        tracing::info!("Running new madara process with args {:?}", args);
 
         let mut cmd = Command::new(target_bin);
-        cmd.envs(self.env)
-            .args(self.args)
+        // Relative paths in the arguments, such as `--chain-config-path test_devnet.yaml`, are resolved from this crate
+        // whichever directory the tests are run from.
+        cmd.current_dir(env!("CARGO_MANIFEST_DIR"))
+            .envs(self.env)
+            .args(args)
             .args(["--base-path".into(), self.tempdir.path().display().to_string()])
+            .args(self.rpc_enabled.then_some(["--rpc-port", rpc_port.as_str()]).into_iter().flatten())
             .args(
-                self.rpc_enabled
+                self.gateway_enabled
                     .then_some([
-                        "--rpc-port",
+                        "--gateway-port",
                         "0", // OS Assigned
                     ])
                     .into_iter()
                     .flatten(),
             )
             .args(
-                self.gateway_enabled
+                self.admin_rpc_enabled
                     .then_some([
-                        "--gateway-port",
+                        "--rpc-admin",
+                        "--rpc-admin-port",
                         "0", // OS Assigned
                     ])
                     .into_iter()
@@ -353,6 +742,7 @@ impl MadaraCmdBuilder {
             ready: false,
             json_rpc: None,
             rpc_url: None,
+            admin_rpc_url: None,
             gateway_root_url: None,
             label: self.label,
             tempdir: self.tempdir,
@@ -370,6 +760,20 @@ fn madara_help_shows() {
     assert!(stdout.contains("Madara: High performance Starknet sequencer/full-node"), "stdout: {stdout}");
 }
 
+#[rstest]
+#[tokio::test]
+async fn madara_exits_cleanly_on_interrupt() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+    let mut node = MadaraCmdBuilder::new().args(["--devnet", "--no-l1-sync", "--gas-price", "0"]).run();
+    node.wait_for_ready().await;
+
+    match node.graceful_stop() {
+        Some(StopOutcome::Exited(status)) => assert!(status.success(), "Madara exited with {status}"),
+        outcome => panic!("Madara did not exit cleanly: {outcome:?}"),
+    }
+}
+
 #[rstest]
 #[tokio::test]
 async fn madara_can_sync_a_few_blocks() {
@@ -479,3 +883,229 @@ async fn madara_can_sync_and_restart() {
         }
     );
 }
+
+#[rstest]
+#[tokio::test]
+async fn madara_can_export_blocks() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+    let mut node = MadaraCmdBuilder::new()
+        .enable_gateway()
+        .args([
+            "--full",
+            "--network",
+            "sepolia",
+            "--sync-stop-at",
+            "2",
+            "--no-l1-sync",
+            "--gas-price",
+            "0",
+            "--gateway",
+        ])
+        .run();
+    node.wait_for_ready().await;
+    node.wait_for_sync_to(2).await;
+    node.wait_for_gateway_ready().await;
+
+    let out = tempfile::tempdir().unwrap();
+    node.export_blocks(0..=2, out.path(), "sepolia").await.unwrap();
+
+    let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("../resources");
+    for file in ["sepolia.block_0_class_0.json", "sepolia.block_0_class_1.json", "sepolia.block_2_class_0.json"] {
+        assert!(out.path().join(file).exists(), "{file} was not exported");
+    }
+    for block_n in 0..=2 {
+        let file = format!("sepolia.block_{block_n}.json");
+        let read = |dir: &Path| -> serde_json::Value {
+            serde_json::from_slice(&std::fs::read(dir.join(&file)).unwrap()).unwrap()
+        };
+        let (exported, expected) = (read(out.path()), read(&resources));
+        assert_eq!(exported["state_update"]["block_hash"], expected["state_update"]["block_hash"]);
+        assert_eq!(exported["state_update"]["state_diff"], expected["state_update"]["state_diff"]);
+    }
+
+    let err = node.export_blocks(2..=3, out.path(), "sepolia").await.unwrap_err();
+    assert!(format!("{err:#}").contains("the latest block is #2"), "{err:#}");
+}
+
+#[rstest]
+#[tokio::test]
+async fn gateway_rejects_oversized_requests() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+    let mut node = MadaraCmdBuilder::new()
+        .enable_gateway()
+        .args([
+            "--devnet",
+            "--no-l1-sync",
+            "--gas-price",
+            "0",
+            "--chain-config-path",
+            "test_devnet.yaml",
+            "--gateway",
+            "--gateway-max-request-size",
+            "1",
+        ])
+        .run();
+    node.wait_for_ready().await;
+
+    // Just over the limit, so that the whole body is sent before the server answers.
+    let res = node.gateway_root_post("/gateway/add_transaction").await.body(vec![b' '; 1024 * 1024 + 1]).send().await;
+    assert_eq!(res.unwrap().status(), reqwest::StatusCode::PAYLOAD_TOO_LARGE);
+
+    // Requests under the limit are read and parsed.
+    let res = node.gateway_root_post("/gateway/add_transaction").await.body("{}").send().await;
+    assert_eq!(res.unwrap().status(), reqwest::StatusCode::BAD_REQUEST);
+}
+
+#[rstest]
+#[tokio::test]
+async fn madara_rpc_serves_additional_addrs() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+    // Any free port will do: it is released right away, for the node to bind it.
+    let additional_addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+    let mut node = MadaraCmdBuilder::new()
+        .args([
+            "--devnet",
+            "--no-l1-sync",
+            "--chain-config-path",
+            "test_devnet.yaml",
+            "--rpc-additional-addrs",
+            additional_addr.to_string().as_str(),
+        ])
+        .run();
+    node.wait_for_ready().await;
+
+    let additional = JsonRpcClient::new(HttpTransport::new(Url::parse(&format!("http://{additional_addr}/")).unwrap()));
+    let chain_id = node.json_rpc().chain_id().await.unwrap();
+    assert_eq!(additional.chain_id().await.unwrap(), chain_id);
+}
+
+#[rstest]
+#[case::sequencer(MadaraProfile::Sequencer, &[], &["--devnet", "--no-l1-sync", "--gas-price", "0"])]
+#[case::full_node(
+    MadaraProfile::FullNode,
+    &["--network", "sepolia"],
+    &["--full", "--no-l1-sync", "--gas-price", "0", "--network", "sepolia"]
+)]
+#[case::archive_node(
+    MadaraProfile::ArchiveNode,
+    &["--network", "sepolia"],
+    &[
+        "--full",
+        "--no-l1-sync",
+        "--gas-price",
+        "0",
+        "--db-max-saved-trie-logs",
+        "1000000",
+        "--db-max-kept-snapshots",
+        "1000",
+        "--rpc-storage-proof-max-distance",
+        "1000000",
+        "--network",
+        "sepolia",
+    ]
+)]
+#[case::overridden_flags(
+    MadaraProfile::ArchiveNode,
+    &["--devnet", "--gas-price=10", "--db-max-saved-trie-logs", "20"],
+    &[
+        "--no-l1-sync",
+        "--db-max-kept-snapshots",
+        "1000",
+        "--rpc-storage-proof-max-distance",
+        "1000000",
+        "--devnet",
+        "--gas-price=10",
+        "--db-max-saved-trie-logs",
+        "20",
+    ]
+)]
+fn madara_profile_args(#[case] profile: MadaraProfile, #[case] args: &[&str], #[case] expected: &[&str]) {
+    let builder = MadaraCmdBuilder::new().profile(profile).args(args.iter().copied());
+    assert_eq!(builder.node_args(), expected);
+}
+
+#[rstest]
+#[tokio::test]
+async fn madara_head() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+    let mut node =
+        MadaraCmdBuilder::new().profile(MadaraProfile::FullNode).args(["--network", "sepolia", "--no-sync"]).run();
+    node.wait_for_ready().await;
+    assert_eq!(node.head().await.unwrap(), None);
+    node.stop();
+
+    let mut node = MadaraCmdBuilder::new()
+        .profile(MadaraProfile::FullNode)
+        .args(["--network", "sepolia", "--sync-stop-at", "2"])
+        .run();
+    node.wait_for_ready().await;
+    node.wait_for_sync_to(2).await;
+
+    let expected: serde_json::Value = serde_json::from_slice(
+        &std::fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("../resources/sepolia.block_2.json")).unwrap(),
+    )
+    .unwrap();
+    let felt = |field: &str| Felt::from_hex(expected["state_update"][field].as_str().unwrap()).unwrap();
+    assert_eq!(
+        node.head().await.unwrap(),
+        Some(BlockHead { number: 2, hash: felt("block_hash"), state_root: felt("new_root") })
+    );
+}
+
+#[rstest]
+#[tokio::test]
+async fn madara_dump_database_reloads() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+    let builder =
+        MadaraCmdBuilder::new().profile(MadaraProfile::FullNode).args(["--network", "sepolia", "--sync-stop-at", "2"]);
+    let mut node = builder.clone().enable_admin_rpc().run();
+    node.wait_for_ready().await;
+    node.wait_for_sync_to(2).await;
+    let head = node.json_rpc().block_hash_and_number().await.unwrap();
+
+    let restored = MadaraCmdBuilder { tempdir: Arc::new(TempDir::with_prefix("madara-test").unwrap()), ..builder };
+    let block_n = node.dump_database(restored.db_dir()).await.unwrap();
+    assert_eq!(block_n, Some(2));
+    node.stop();
+
+    let mut restored = restored.run();
+    restored.wait_for_ready().await;
+    let restored_head = restored.json_rpc().block_hash_and_number().await.unwrap();
+    assert_eq!((restored_head.block_number, restored_head.block_hash), (head.block_number, head.block_hash));
+
+    let err = restored.dump_database(node.db_dir()).await.unwrap_err();
+    assert!(matches!(err, SnapshotFailure::CheckpointNotAcknowledged(_)), "{err}");
+}
+
+#[rstest]
+#[tokio::test]
+async fn madara_rpc_admin_optional() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+    // Hold the admin port for the whole test.
+    let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let admin_port = taken.local_addr().unwrap().port();
+
+    let mut node = MadaraCmdBuilder::new()
+        .args([
+            "--devnet",
+            "--no-l1-sync",
+            "--chain-config-path",
+            "test_devnet.yaml",
+            "--rpc-admin",
+            "--rpc-admin-optional",
+            "--rpc-admin-port",
+            admin_port.to_string().as_str(),
+        ])
+        .run();
+    node.wait_for_ready().await;
+
+    node.json_rpc().chain_id().await.unwrap();
+    drop(taken);
+}
@@ -822,6 +822,36 @@ mod test_rpc_read_calls {
         assert_type_equality(&events.continuation_token, &expected_events.continuation_token);
     }
 
+    #[rstest]
+    #[tokio::test]
+    async fn test_get_events_follows_continuation_tokens() {
+        let madara = get_madara().await;
+        let filter = EventFilter {
+            from_block: Some(BlockId::Number(0)),
+            to_block: Some(BlockId::Number(19)),
+            address: Some(felt!("0x49d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7")),
+            keys: Some(vec![vec![]]),
+        };
+
+        let mut expected = vec![];
+        let mut continuation_token = None;
+        loop {
+            let page = madara.json_rpc().get_events(filter.clone(), continuation_token, 2).await.unwrap();
+            expected.extend(page.events);
+            match page.continuation_token {
+                Some(token) => continuation_token = Some(token),
+                None => break,
+            }
+        }
+
+        let events = madara.get_events(filter.clone()).await.unwrap();
+        assert!(events.len() > 2);
+        assert_eq!(events, expected);
+
+        let empty = madara.get_events(EventFilter { address: Some(felt!("0xdead")), ..filter }).await.unwrap();
+        assert_eq!(empty, vec![]);
+    }
+
     /// Retrieves events based on a filter with a continuation token.
     ///
     /// Example curl command:
@@ -0,0 +1,161 @@
+//! A websocket subscription client which survives node restarts.
+
+use crate::{MadaraCmd, MadaraCmdBuilder};
+use futures::{Stream, StreamExt};
+use jsonrpsee::core::client::{Subscription, SubscriptionClientT};
+use jsonrpsee::core::params::ObjectParams;
+use jsonrpsee::ws_client::{WsClient, WsClientBuilder};
+use rstest::rstest;
+use starknet_providers::Provider;
+use std::time::Duration;
+
+const RECONNECT_BACKOFF_MIN: Duration = Duration::from_millis(100);
+const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(5);
+
+/// Subscribes to a Madara websocket endpoint, and transparently reconnects and resubscribes whenever the connection
+/// is lost, for example when the node is restarted in the middle of a test. The node needs to be restarted on the
+/// same port for the reconnection to succeed.
+///
+/// Notifications received before the connection was lost are not replayed: depending on the subscription, items may
+/// be missed or received twice across a reconnection.
+#[derive(Debug, Clone)]
+pub struct MadaraSubscriptionClient {
+    url: String,
+    subscribe_method: String,
+    unsubscribe_method: String,
+    params: serde_json::Map<String, serde_json::Value>,
+}
+
+impl MadaraSubscriptionClient {
+    /// `url` is the websocket url, including the rpc version path, for example `ws://127.0.0.1:9944/rpc/v0_8_0/`.
+    pub fn new(
+        url: impl Into<String>,
+        subscribe_method: impl Into<String>,
+        unsubscribe_method: impl Into<String>,
+        params: serde_json::Map<String, serde_json::Value>,
+    ) -> Self {
+        Self {
+            url: url.into(),
+            subscribe_method: subscribe_method.into(),
+            unsubscribe_method: unsubscribe_method.into(),
+            params,
+        }
+    }
+
+    /// Subscribes to `starknet_subscribeNewHeads` starting from the latest block.
+    pub fn new_heads(node: &MadaraCmd) -> Self {
+        let params = serde_json::json!({ "block": "latest" }).as_object().cloned().unwrap_or_default();
+        Self::new(node.ws_url("v0_8_0"), "starknet_subscribeNewHeads", "starknet_unsubscribeNewHeads", params)
+    }
+
+    /// Notifications from the subscription. This stream never ends: it keeps reconnecting, with an exponential
+    /// backoff, until it is dropped.
+    pub fn into_stream(self) -> impl Stream<Item = serde_json::Value> {
+        let state: Option<(WsClient, Subscription<serde_json::Value>)> = None;
+        futures::stream::unfold((self, state), |(this, state)| async move {
+            let (mut client, mut sub) = match state {
+                Some(state) => state,
+                None => this.connect().await,
+            };
+            loop {
+                match sub.next().await {
+                    Some(Ok(item)) => return Some((item, (this, Some((client, sub))))),
+                    Some(Err(err)) => tracing::warn!("Subscription {} errored: {err:#}", this.subscribe_method),
+                    None => tracing::info!("Subscription {} closed, reconnecting", this.subscribe_method),
+                }
+                drop((client, sub));
+                (client, sub) = this.connect().await;
+            }
+        })
+    }
+
+    async fn connect(&self) -> (WsClient, Subscription<serde_json::Value>) {
+        let mut backoff = RECONNECT_BACKOFF_MIN;
+        loop {
+            match self.try_connect().await {
+                Ok(res) => return res,
+                Err(err) => {
+                    tracing::debug!("Could not subscribe to {} at {}: {err:#}", self.subscribe_method, self.url)
+                }
+            }
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(RECONNECT_BACKOFF_MAX);
+        }
+    }
+
+    async fn try_connect(&self) -> anyhow::Result<(WsClient, Subscription<serde_json::Value>)> {
+        let client = WsClientBuilder::default().build(&self.url).await?;
+        let mut params = ObjectParams::new();
+        for (key, value) in &self.params {
+            params.insert(key, value)?;
+        }
+        let sub = client.subscribe(&self.subscribe_method, params, &self.unsubscribe_method).await?;
+        Ok((client, sub))
+    }
+}
+
+#[rstest]
+#[tokio::test]
+async fn madara_subscription_client_receives_new_heads() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+    let mut node = MadaraCmdBuilder::new()
+        .args([
+            "--devnet",
+            "--no-l1-sync",
+            "--gas-price",
+            "0",
+            "--chain-config-override",
+            "block_time=1s,pending_block_update_time=null",
+        ])
+        .run();
+    node.wait_for_ready().await;
+
+    let heads: Vec<_> = tokio::time::timeout(
+        Duration::from_secs(30),
+        MadaraSubscriptionClient::new_heads(&node).into_stream().take(2).collect(),
+    )
+    .await
+    .expect("Waiting for new heads");
+
+    let block_n = |head: &serde_json::Value| head["block_number"].as_u64().expect("Block number in new head");
+    assert!(block_n(&heads[1]) > block_n(&heads[0]), "Heads are not increasing: {heads:?}");
+}
+
+#[rstest]
+#[tokio::test]
+async fn madara_subscription_client_resumes_after_restart() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+    let cmd_builder = MadaraCmdBuilder::new().args([
+        "--devnet",
+        "--no-l1-sync",
+        "--gas-price",
+        "0",
+        "--chain-config-override",
+        "block_time=1s,pending_block_update_time=null",
+    ]);
+    let mut node = cmd_builder.clone().run();
+    node.wait_for_ready().await;
+    let rpc_port = node.rpc_url.as_ref().and_then(|url| url.port()).expect("RPC port");
+
+    let block_n = |head: &serde_json::Value| head["block_number"].as_u64().expect("Block number in new head");
+    let mut heads = Box::pin(MadaraSubscriptionClient::new_heads(&node).into_stream());
+    tokio::time::timeout(Duration::from_secs(30), heads.next()).await.expect("Waiting for a new head");
+
+    node.stop();
+    let mut node = cmd_builder.rpc_port(rpc_port).run();
+    node.wait_for_ready().await;
+    // The restarted node runs on the same database, so any block above this one was produced after the restart.
+    let restarted_at = node.json_rpc().block_number().await.unwrap();
+
+    tokio::time::timeout(Duration::from_secs(60), async {
+        while let Some(head) = heads.next().await {
+            if block_n(&head) > restarted_at {
+                return;
+            }
+        }
+    })
+    .await
+    .expect("Waiting for a new head from the restarted node");
+}
@@ -114,6 +114,7 @@ impl SetupBuilder {
             .args(self.sequencer_args().chain(["--gateway-trusted-add-transaction-endpoint".into()]))
             .run();
         sequencer.wait_for_sync_to(0).await; // wait until devnet genesis is deployed
+        sequencer.wait_for_gateway_ready().await;
 
         let mut gateway = MadaraCmdBuilder::new()
             .label("gateway")
@@ -145,6 +146,7 @@ impl SetupBuilder {
         let mut sequencer =
             MadaraCmdBuilder::new().label("sequencer").enable_gateway().args(self.sequencer_args()).run();
         sequencer.wait_for_sync_to(0).await;
+        sequencer.wait_for_gateway_ready().await;
 
         let mut full_node = MadaraCmdBuilder::new()
             .label("full_node")
@@ -260,6 +262,50 @@ impl RunningTestSetup {
         // can only be called via jsonrpc
         self.json_rpc().get_nonce(BlockId::Tag(BlockTag::Pending), contract_address).await.unwrap()
     }
+
+    /// Declares the sierra class using the devnet account, unless it is already declared, then deploys it using
+    /// the UDC. Returns once both transactions have a successful receipt.
+    pub async fn declare_and_deploy(
+        &self,
+        sierra: &[u8],
+        compiled_class_hash: Felt,
+        salt: Felt,
+        constructor_calldata: Vec<Felt>,
+    ) -> DeployedContract {
+        let sierra_class: starknet_core::types::contract::SierraClass = serde_json::from_slice(sierra).unwrap();
+        let flattened_class = Arc::new(sierra_class.flatten().unwrap());
+        let class_hash = flattened_class.class_hash();
+        let account = self.account(self.json_rpc()).await;
+
+        match self.json_rpc().get_class(BlockId::Tag(BlockTag::Pending), class_hash).await {
+            Ok(_) => tracing::debug!("Class {class_hash:#x} is already declared"),
+            Err(ProviderError::StarknetError(StarknetError::ClassHashNotFound)) => {
+                let res = account
+                    .declare_v3(flattened_class, compiled_class_hash)
+                    .gas_price(0x5000)
+                    .gas(0x10000000000)
+                    .send()
+                    .await
+                    .unwrap();
+                self.expect_tx_receipt_successful(res.transaction_hash).await;
+            }
+            Err(err) => panic!("Getting class {class_hash:#x}: {err:#}"),
+        }
+
+        let factory = ContractFactory::new(class_hash, account);
+        let deployment = factory.deploy_v3(constructor_calldata, salt, /* unique */ true);
+        let address = deployment.deployed_address();
+        let res = deployment.gas_price(0x5000).gas(0x10000000000).send().await.unwrap();
+        self.expect_tx_receipt_successful(res.transaction_hash).await;
+
+        DeployedContract { class_hash, address }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DeployedContract {
+    class_hash: Felt,
+    address: Felt,
 }
 
 fn make_transfer_call(recipient: Felt, amount: u128) -> Vec<Call> {
@@ -837,3 +883,43 @@ async fn declare_sierra_then_deploy(
         perform_test(&setup, setup.json_rpc()).await;
     }
 }
+
+#[rstest]
+#[tokio::test]
+/// Deploying the same class twice only declares it once.
+async fn declare_and_deploy_twice() {
+    let setup = SetupBuilder::new(SequencerOnly).with_block_time("500ms").run().await;
+    // starkli class-hash target/dev/madara_contracts_TestContract.compiled_contract_class.json
+    let compiled_contract_class_hash =
+        Felt::from_hex_unchecked("0x0138105ded3d2e4ea1939a0bc106fb80fd8774c9eb89c1890d4aeac88e6a1b27");
+    let key = SigningKey::from_secret_scalar(Felt::from_hex_unchecked("0x273623"));
+
+    let first = setup
+        .declare_and_deploy(
+            m_cairo_test_contracts::TEST_CONTRACT_SIERRA,
+            compiled_contract_class_hash,
+            Felt::TWO,
+            vec![key.verifying_key().scalar()],
+        )
+        .await;
+    let nonce = setup.get_nonce(ACCOUNT_ADDRESS).await;
+
+    let second = setup
+        .declare_and_deploy(
+            m_cairo_test_contracts::TEST_CONTRACT_SIERRA,
+            compiled_contract_class_hash,
+            Felt::THREE,
+            vec![key.verifying_key().scalar()],
+        )
+        .await;
+
+    assert_eq!(first.class_hash, second.class_hash);
+    assert_ne!(first.address, second.address);
+    // Only the deploy transaction was sent the second time.
+    assert_eq!(setup.get_nonce(ACCOUNT_ADDRESS).await, nonce + Felt::ONE);
+    for contract in [first, second] {
+        let class_hash =
+            setup.json_rpc().get_class_hash_at(BlockId::Tag(BlockTag::Pending), contract.address).await.unwrap();
+        assert_eq!(class_hash, contract.class_hash);
+    }
+}
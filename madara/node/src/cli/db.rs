@@ -115,6 +115,23 @@ pub struct DbParams {
     /// Set the rocksdb prefix bloom filter ratio.
     #[clap(env = "MADARA_DB_MEMTABLE_PREFIX_BLOOM_FILTER_RATIO", long, default_value_t = 0.0)]
     pub db_memtable_prefix_bloom_filter_ratio: f64,
+
+    /// Size of the rocksdb block cache in MiB, shared between all columns. A larger cache keeps more of the
+    /// database in memory and speeds up reads during sync. Values between 512 and roughly a third of the available
+    /// system memory are safe. By default, rocksdb uses a small cache per column.
+    #[clap(env = "MADARA_DB_BLOCK_CACHE_SIZE_MIB", long, value_name = "MIB")]
+    pub db_block_cache_size_mib: Option<usize>,
+
+    /// Total memtable size across all columns in MiB, after which memtables are flushed to disk. Higher values
+    /// reduce write amplification at the cost of memory usage. This should stay below the sum of the memtable
+    /// budgets, and below a quarter of the available system memory. By default, only the per-column budgets apply.
+    #[clap(env = "MADARA_DB_WRITE_BUFFER_SIZE_MIB", long, value_name = "MIB")]
+    pub db_write_buffer_size_mib: Option<usize>,
+
+    /// Maximum number of concurrent rocksdb background jobs (flushes and compactions). Values between 2 and twice
+    /// the number of cores are safe. Defaults to the number of available cores.
+    #[clap(env = "MADARA_DB_MAX_BACKGROUND_JOBS", long, value_name = "COUNT")]
+    pub db_max_background_jobs: Option<usize>,
 }
 
 impl DbParams {
@@ -138,6 +155,9 @@ impl DbParams {
                 memtable_contracts_budget_mib: self.db_memtable_contracts_budget_mib,
                 memtable_other_budget_mib: self.db_memtable_other_budget_mib,
                 memtable_prefix_bloom_filter_ratio: self.db_memtable_prefix_bloom_filter_ratio,
+                block_cache_size_mib: self.db_block_cache_size_mib,
+                write_buffer_size_mib: self.db_write_buffer_size_mib,
+                max_background_jobs: self.db_max_background_jobs,
             },
         }
     }
@@ -4,6 +4,8 @@ use serde::{Deserialize, Serialize};
 
 /// The default port.
 pub const FGW_DEFAULT_PORT: u16 = 8080;
+/// The default max request size in MiB.
+pub const GATEWAY_DEFAULT_MAX_REQUEST_SIZE_MIB: u32 = 15;
 
 /// Parameters used to config gateway.
 #[derive(Debug, Clone, Args, Deserialize, Serialize)]
@@ -31,6 +33,11 @@ pub struct GatewayParams {
     /// The gateway port to listen on.
     #[arg(env = "MADARA_GATEWAY_PORT", long, value_name = "PORT", default_value_t = FGW_DEFAULT_PORT)]
     pub gateway_port: u16,
+
+    /// Set the maximum gateway request body size in mebibytes. Larger requests are rejected with a
+    /// `413 Payload Too Large`.
+    #[arg(env = "MADARA_GATEWAY_MAX_REQUEST_SIZE", long, default_value_t = GATEWAY_DEFAULT_MAX_REQUEST_SIZE_MIB)]
+    pub gateway_max_request_size: u32,
 }
 
 impl GatewayParams {
@@ -41,6 +48,7 @@ impl GatewayParams {
             gateway_external: self.gateway_external,
             gateway_port: self.gateway_port,
             enable_trusted_add_validated_transaction: self.gateway_trusted_add_transaction_endpoint,
+            max_request_body_size: (self.gateway_max_request_size as usize).saturating_mul(1024 * 1024),
         }
     }
 
@@ -5,6 +5,7 @@ use mc_gateway_client::GatewayProvider;
 use mp_chain_config::ChainConfig;
 use mp_utils::parsers::parse_url;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::sync::Arc;
 use url::Url;
 
@@ -28,6 +29,24 @@ pub struct L2SyncParams {
     #[clap(env = "MADARA_DISABLE_TRIES", long)]
     pub disable_tries: bool,
 
+    /// Do not compute the global tries, and trust the state roots of the synced block headers instead.
+    /// Unlike `--disable-tries`, the tries are not given up on: the skipped blocks are applied and their
+    /// state roots verified once the node is restarted without this flag. Only use this when syncing from
+    /// a source you trust, such as your own node.
+    #[clap(env = "MADARA_TRUST_GLOBAL_TRIE", long, conflicts_with = "disable_tries")]
+    pub trust_global_trie: bool,
+
+    /// Profile the global tries computation: the time spent applying each block to the global tries and its number
+    /// of storage updates are appended to this CSV file. Blocks are then applied one at a time, which makes syncing
+    /// slower.
+    #[clap(env = "MADARA_GLOBAL_TRIE_TIMINGS", long, value_name = "PATH", conflicts_with_all = ["disable_tries", "trust_global_trie"])]
+    pub global_trie_timings: Option<PathBuf>,
+
+    /// Check the global state root after every block when applying a batch of blocks to the global tries, instead of
+    /// only for the last block of the batch. A corrupt state diff is then reported at the block that introduced it.
+    #[clap(env = "MADARA_VERIFY_EACH_GLOBAL_STATE_ROOT", long, conflicts_with_all = ["disable_tries", "trust_global_trie"])]
+    pub verify_each_global_state_root: bool,
+
     /// Gateway api key to avoid rate limiting (optional).
     #[clap(env = "MADARA_GATEWAY_KEY", long, value_name = "API KEY")]
     pub gateway_key: Option<String>,
@@ -3,6 +3,7 @@ use mc_rpc::StorageProofConfig;
 use serde::{Deserialize, Serialize};
 use std::convert::Infallible;
 use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
 use std::str::FromStr;
 
 /// The default port.
@@ -78,6 +79,11 @@ pub struct RpcParams {
     #[arg(env = "MADARA_RPC_ADMIN_EXTERNAL", long, default_value_t = false)]
     pub rpc_admin_external: bool,
 
+    /// Keep the node running without the admin RPC endpoint when its address cannot be bound, for example because
+    /// the port is already in use. By default, this is a fatal error.
+    #[arg(env = "MADARA_RPC_ADMIN_OPTIONAL", long, default_value_t = false)]
+    pub rpc_admin_optional: bool,
+
     /// Set the maximum RPC request payload size for both HTTP and WebSockets in mebibytes.
     #[arg(env = "MADARA_RPC_MAX_REQUEST_SIZE", long, default_value_t = RPC_DEFAULT_MAX_REQUEST_SIZE_MIB)]
     pub rpc_max_request_size: u32,
@@ -94,11 +100,23 @@ pub struct RpcParams {
     #[arg(env = "MADARA_RPC_PORT", long, value_name = "PORT", default_value_t = RPC_DEFAULT_PORT)]
     pub rpc_port: u16,
 
+    /// Additional addresses to serve the user RPC on, as a comma separated list. This can be used to listen on both
+    /// IPv4 and IPv6, for example with `--rpc-external --rpc-additional-addrs [::]:9944`.
+    #[arg(env = "MADARA_RPC_ADDITIONAL_ADDRS", long, value_name = "ADDRS", value_delimiter = ',')]
+    pub rpc_additional_addrs: Vec<SocketAddr>,
+
+    /// Also serve the user RPC on this unix domain socket, for services running on the same host.
+    /// Requests made over the socket are not subject to host filtering. A socket left at this path by a previous run is
+    /// replaced, but any other file makes the node fail to start. The socket is removed on shutdown.
+    #[arg(env = "MADARA_RPC_UDS_PATH", long, value_name = "PATH")]
+    pub rpc_uds_path: Option<PathBuf>,
+
     /// The RPC port to listen at for admin RPC calls.
     #[arg(env = "MADARA_RPC_PORT_ADMIN", long, value_name = "ADMIN PORT", default_value_t = RPC_DEFAULT_PORT_ADMIN)]
     pub rpc_admin_port: u16,
 
-    /// Maximum number of RPC server connections at a given time.
+    /// Maximum number of RPC server connections at a given time. The limit is shared by all the addresses and the
+    /// unix socket the server listens on.
     #[arg(env = "MADARA_RPC_MAX_CONNECTIONS", long, value_name = "COUNT", default_value_t = RPC_DEFAULT_MAX_CONNECTIONS)]
     pub rpc_max_connections: u32,
 
@@ -115,6 +133,29 @@ pub struct RpcParams {
     #[arg(env = "MADARA_RPC_MAX_BATCH_REQUEST_LEN", long, conflicts_with_all = &["rpc_disable_batch_requests"], value_name = "LEN")]
     pub rpc_max_batch_request_len: Option<u32>,
 
+    /// Log a warning for every RPC call taking longer than this many milliseconds to complete.
+    /// Disabled by default.
+    #[arg(env = "MADARA_RPC_SLOW_REQUEST_MS", long, value_name = "MILLIS")]
+    pub rpc_slow_request_ms: Option<u64>,
+
+    /// Close connections which have received no request for this many milliseconds. Connections with a call in
+    /// flight or an open subscription are never considered idle. Websocket sessions are closed with a close frame.
+    /// Disabled by default.
+    #[arg(env = "MADARA_RPC_CONNECTION_IDLE_TIMEOUT_MS", long, value_name = "MILLIS")]
+    pub rpc_connection_idle_timeout_ms: Option<u64>,
+
+    /// Compress HTTP responses with gzip or brotli, when the client supports it through the `Accept-Encoding`
+    /// header. WebSocket messages are never compressed.
+    #[arg(env = "MADARA_RPC_COMPRESSION", long, default_value_t = false)]
+    pub rpc_compression: bool,
+
+    /// Answer calls which are missing an id instead of treating them as notifications. The JSON-RPC spec says such
+    /// calls must not be answered, but some client libraries omit the id on regular requests: in this mode they get a
+    /// response with a `null` id. Calls to methods which are used for their effect, such as adding a transaction, are
+    /// still treated as notifications. Numeric and string ids are accepted in both modes.
+    #[arg(env = "MADARA_RPC_LENIENT_IDS", long, default_value_t = false)]
+    pub rpc_lenient_ids: bool,
+
     /// Specify browser *origins* allowed to access the HTTP & WebSocket RPC
     /// servers.
     ///
@@ -156,6 +197,11 @@ pub struct RpcParams {
     /// storage is queried count as one each.
     #[arg(env = "MADARA_RPC_STORAGE_PROOF_MAX_TRIES", long, default_value_t = 5)]
     pub rpc_storage_proof_max_tries: usize,
+
+    /// Keep this many compiled classes returned by `starknet_getCompiledCasm` in memory, so that the classes
+    /// requested most often are not read and parsed again from the database. Disabled by default.
+    #[arg(env = "MADARA_RPC_CASM_CACHE_SIZE", long, default_value_t = 0, value_name = "CLASSES")]
+    pub rpc_casm_cache_size: usize,
 }
 
 impl RpcParams {
@@ -189,6 +235,11 @@ impl RpcParams {
         SocketAddr::new(listen_addr.into(), self.rpc_port)
     }
 
+    /// All the addresses the user RPC is served on.
+    pub fn addrs_user(&self) -> Vec<SocketAddr> {
+        std::iter::once(self.addr_user()).chain(self.rpc_additional_addrs.iter().copied()).collect()
+    }
+
     pub fn addr_admin(&self) -> SocketAddr {
         let listen_addr = if self.rpc_admin_external {
             Ipv4Addr::UNSPECIFIED // listen on 0.0.0.0
@@ -327,12 +327,14 @@ async fn main() -> anyhow::Result<()> {
     // User-facing RPC
 
     let service_rpc_user =
-        RpcService::user(run_cmd.rpc_params.clone(), Arc::clone(service_db.backend()), tx_submit.clone());
+        RpcService::user(run_cmd.rpc_params.clone(), Arc::clone(service_db.backend()), tx_submit.clone())
+            .with_sync_phase_recv(service_l2_sync.sync_phase_recv());
 
     // Admin-facing RPC (for node operators)
 
     let service_rpc_admin =
-        RpcService::admin(run_cmd.rpc_params.clone(), Arc::clone(service_db.backend()), tx_submit.clone());
+        RpcService::admin(run_cmd.rpc_params.clone(), Arc::clone(service_db.backend()), tx_submit.clone())
+            .with_sync_phase_recv(service_l2_sync.sync_phase_recv());
 
     // Feeder gateway
 
@@ -5,7 +5,7 @@ use mc_rpc::versions::admin::v0_1_0::MadaraStatusRpcApiV0_1_0Client;
 use mc_settlement_client::state_update::L1HeadReceiver;
 use mc_sync::{
     import::{BlockImporter, BlockValidationConfig},
-    SyncControllerConfig,
+    SyncControllerConfig, SyncPhaseReceiver, SyncPhaseSender,
 };
 use mp_utils::service::{MadaraServiceId, PowerOfTwo, Service, ServiceId, ServiceRunner};
 use std::sync::Arc;
@@ -33,12 +33,14 @@ struct StartArgs {
     db_backend: Arc<MadaraBackend>,
     params: L2SyncParams,
     warp_update: Option<WarpUpdateConfig>,
+    sync_phase_sender: SyncPhaseSender,
 }
 
 #[derive(Clone)]
 pub struct SyncService {
     start_args: Option<StartArgs>,
     disabled: bool,
+    sync_phase_recv: SyncPhaseReceiver,
 }
 
 impl SyncService {
@@ -48,16 +50,25 @@ impl SyncService {
         l1_head_recv: L1HeadReceiver,
         warp_update: Option<WarpUpdateConfig>,
     ) -> anyhow::Result<Self> {
+        let (sync_phase_sender, sync_phase_recv) = tokio::sync::watch::channel(None);
         Ok(Self {
             start_args: (!config.l2_sync_disabled).then_some(StartArgs {
                 l1_head_recv,
                 db_backend: db.clone(),
                 params: config.clone(),
                 warp_update,
+                sync_phase_sender,
             }),
             disabled: config.l2_sync_disabled,
+            sync_phase_recv,
         })
     }
+
+    /// Notified whenever the node goes from catching up to being at the tip of the chain, and back. [`None`] when
+    /// the sync is disabled.
+    pub fn sync_phase_recv(&self) -> Option<SyncPhaseReceiver> {
+        (!self.disabled).then(|| self.sync_phase_recv.clone())
+    }
 }
 
 #[async_trait::async_trait]
@@ -69,7 +80,11 @@ impl Service for SyncService {
         let this = self.start_args.take().expect("Service already started");
         let importer = Arc::new(BlockImporter::new(
             this.db_backend.clone(),
-            BlockValidationConfig::default().trust_parent_hash(this.params.unsafe_starting_block.is_some()),
+            BlockValidationConfig::default()
+                .trust_parent_hash(this.params.unsafe_starting_block.is_some())
+                .trust_global_trie(this.params.trust_global_trie)
+                .global_trie_timings(this.params.global_trie_timings.clone())
+                .verify_each_global_state_root(this.params.verify_each_global_state_root),
         ));
 
         let config = SyncControllerConfig::default()
@@ -77,7 +92,8 @@ impl Service for SyncService {
             .stop_at_block_n(this.params.sync_stop_at)
             .global_stop_on_sync(this.params.stop_on_sync)
             .stop_on_sync(this.params.stop_on_sync)
-            .no_pending_block(this.params.no_pending_sync);
+            .no_pending_block(this.params.no_pending_sync)
+            .sync_phase_sender(this.sync_phase_sender);
 
         if let Some(starting_block) = this.params.unsafe_starting_block {
             // We state that starting_block - 1 is the chain head.
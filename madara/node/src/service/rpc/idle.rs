@@ -0,0 +1,243 @@
+//! Closing of RPC connections which have been idle for too long.
+
+use hyper::server::accept::Accept;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::{Instant, Sleep};
+
+/// Activity of a single connection. A connection is busy while it has a call in flight or an open subscription, and
+/// idle otherwise.
+#[derive(Debug)]
+pub struct ConnectionActivity {
+    last_activity: Mutex<Instant>,
+    in_flight: AtomicUsize,
+    subscriptions: AtomicUsize,
+    /// Set once the connection has been upgraded to a websocket session, which is then in charge of closing it.
+    websocket: AtomicBool,
+}
+
+impl Default for ConnectionActivity {
+    fn default() -> Self {
+        Self {
+            last_activity: Mutex::new(Instant::now()),
+            in_flight: Default::default(),
+            subscriptions: Default::default(),
+            websocket: Default::default(),
+        }
+    }
+}
+
+impl ConnectionActivity {
+    pub fn touch(&self) {
+        *self.last_activity.lock().expect("Poisoned lock") = Instant::now();
+    }
+
+    /// The connection is busy until the returned guard is dropped.
+    pub fn call_started(self: &Arc<Self>) -> InFlightCall {
+        self.touch();
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlightCall(Arc::clone(self))
+    }
+
+    /// The connection is busy until the returned guard is dropped, which should happen once the subscription has
+    /// ended - whether it was closed by the client or by the server.
+    pub fn subscription_opened(self: &Arc<Self>) -> OpenSubscription {
+        self.subscriptions.fetch_add(1, Ordering::SeqCst);
+        OpenSubscription(Arc::clone(self))
+    }
+
+    /// Stops closing the connection at the stream level: websocket sessions are closed with a close frame instead,
+    /// once [`ConnectionActivity::idle`] resolves. Reads no longer count as activity, as they include the pongs
+    /// answering the pings of the server.
+    pub fn upgraded_to_websocket(&self) {
+        self.websocket.store(true, Ordering::SeqCst);
+    }
+
+    /// Resolves once the connection has been idle for longer than `timeout`.
+    pub async fn idle(&self, timeout: Duration) {
+        loop {
+            // While the connection is busy, check again after a full timeout.
+            let deadline = self.idle_deadline(timeout).unwrap_or_else(|| Instant::now() + timeout);
+            if deadline <= Instant::now() {
+                return;
+            }
+            tokio::time::sleep_until(deadline).await;
+        }
+    }
+
+    /// When the connection will be considered idle, or [`None`] if it is busy.
+    fn idle_deadline(&self, timeout: Duration) -> Option<Instant> {
+        if self.in_flight.load(Ordering::SeqCst) > 0 || self.subscriptions.load(Ordering::SeqCst) > 0 {
+            return None;
+        }
+        Some(*self.last_activity.lock().expect("Poisoned lock") + timeout)
+    }
+}
+
+pub struct InFlightCall(Arc<ConnectionActivity>);
+
+impl Drop for InFlightCall {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+        self.0.touch();
+    }
+}
+
+pub struct OpenSubscription(Arc<ConnectionActivity>);
+
+impl Drop for OpenSubscription {
+    fn drop(&mut self) {
+        self.0.subscriptions.fetch_sub(1, Ordering::SeqCst);
+        self.0.touch();
+    }
+}
+
+/// A connection stream which reports the end of the stream once its connection has been idle for longer than the
+/// timeout, making hyper close the connection. Websocket sessions are left to close themselves.
+pub struct IdleTimeoutStream<S> {
+    inner: S,
+    activity: Arc<ConnectionActivity>,
+    timeout: Option<Duration>,
+    sleep: Pin<Box<Sleep>>,
+}
+
+impl<S> IdleTimeoutStream<S> {
+    pub fn new(inner: S, timeout: Option<Duration>) -> Self {
+        Self {
+            inner,
+            activity: Default::default(),
+            timeout,
+            sleep: Box::pin(tokio::time::sleep(timeout.unwrap_or_default())),
+        }
+    }
+
+    pub fn activity(&self) -> Arc<ConnectionActivity> {
+        Arc::clone(&self.activity)
+    }
+
+    /// Returns true once the connection has timed out. Otherwise, makes sure the task is woken up to check again.
+    fn poll_timed_out(&mut self, cx: &mut Context<'_>) -> bool {
+        let Some(timeout) = self.timeout else { return false };
+        if self.activity.websocket.load(Ordering::SeqCst) {
+            return false;
+        }
+        // While the connection is busy, check again after a full timeout.
+        let deadline = self.activity.idle_deadline(timeout).unwrap_or_else(|| Instant::now() + timeout);
+        if deadline <= Instant::now() {
+            return true;
+        }
+        self.sleep.as_mut().reset(deadline);
+        self.sleep.as_mut().poll(cx).is_ready()
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for IdleTimeoutStream<S> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let filled = buf.filled().len();
+        match Pin::new(&mut this.inner).poll_read(cx, buf) {
+            Poll::Ready(res) => {
+                if buf.filled().len() > filled && !this.activity.websocket.load(Ordering::SeqCst) {
+                    this.activity.touch();
+                }
+                Poll::Ready(res)
+            }
+            Poll::Pending if this.poll_timed_out(cx) => {
+                tracing::debug!("Closing idle RPC connection");
+                Poll::Ready(Ok(()))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for IdleTimeoutStream<S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+/// Wraps every accepted connection into an [`IdleTimeoutStream`].
+pub struct IdleTimeoutAccept<A> {
+    inner: A,
+    timeout: Option<Duration>,
+}
+
+impl<A> IdleTimeoutAccept<A> {
+    pub fn new(inner: A, timeout: Option<Duration>) -> Self {
+        Self { inner, timeout }
+    }
+}
+
+impl<A: Accept + Unpin> Accept for IdleTimeoutAccept<A> {
+    type Conn = IdleTimeoutStream<A::Conn>;
+    type Error = A::Error;
+
+    fn poll_accept(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+        let this = self.get_mut();
+        let timeout = this.timeout;
+        Pin::new(&mut this.inner)
+            .poll_accept(cx)
+            .map(|conn| conn.map(|conn| conn.map(|conn| IdleTimeoutStream::new(conn, timeout))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn get(stream: &mut tokio::net::TcpStream) -> String {
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        let mut buf = vec![0; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        String::from_utf8_lossy(&buf[..n]).into_owned()
+    }
+
+    #[tokio::test]
+    async fn idle_connection_is_closed() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let incoming = hyper::server::conn::AddrIncoming::from_listener(listener).unwrap();
+
+        let make_service = hyper::service::make_service_fn(|conn: &IdleTimeoutStream<_>| {
+            let activity = conn.activity();
+            async move {
+                Ok::<_, Infallible>(hyper::service::service_fn(move |_req| {
+                    let _call = activity.call_started();
+                    async { Ok::<_, Infallible>(hyper::Response::new(hyper::Body::from("OK"))) }
+                }))
+            }
+        });
+        let timeout = Duration::from_millis(300);
+        tokio::spawn(hyper::Server::builder(IdleTimeoutAccept::new(incoming, Some(timeout))).serve(make_service));
+
+        let mut idle = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut active = tokio::net::TcpStream::connect(addr).await.unwrap();
+        assert!(get(&mut idle).await.starts_with("HTTP/1.1 200 OK"));
+
+        for _ in 0..10 {
+            tokio::time::sleep(timeout / 3).await;
+            assert!(get(&mut active).await.starts_with("HTTP/1.1 200 OK"));
+        }
+
+        // The server closed the idle connection.
+        let mut buf = vec![0; 16];
+        assert_eq!(idle.read(&mut buf).await.unwrap(), 0);
+    }
+}
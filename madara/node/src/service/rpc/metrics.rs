@@ -1,13 +1,17 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::Instant;
 
 use jsonrpsee::types::Request;
 use jsonrpsee::MethodResponse;
 use opentelemetry::{
     global::Error,
-    metrics::{Counter, Histogram},
+    metrics::{Counter, Gauge, Histogram},
 };
 
-use mc_analytics::{register_counter_metric_instrument, register_histogram_metric_instrument};
+use mc_analytics::{
+    register_counter_metric_instrument, register_gauge_metric_instrument, register_histogram_metric_instrument,
+};
 use opentelemetry::{global, KeyValue};
 
 /// Metrics for RPC middleware storing information about the number of requests started/completed,
@@ -26,6 +30,12 @@ pub struct RpcMetrics {
     ws_sessions_closed: Option<Counter<u64>>,
     /// Histogram over RPC websocket sessions.
     ws_sessions_time: Histogram<f64>,
+    /// Number of connections currently open.
+    active_connections: Gauge<u64>,
+    /// Number of subscriptions currently open, across all connections.
+    active_subscriptions: Gauge<u64>,
+    connections_count: Arc<AtomicU64>,
+    subscriptions_count: Arc<AtomicU64>,
 }
 
 impl RpcMetrics {
@@ -81,7 +91,55 @@ impl RpcMetrics {
             "".to_string(),
         );
 
-        Ok(Self { calls_time, calls_started, calls_finished, ws_sessions_opened, ws_sessions_closed, ws_sessions_time })
+        let active_connections = register_gauge_metric_instrument(
+            &rpc_meter,
+            "active_connections".to_string(),
+            "A gauge to show the number of connections currently open".to_string(),
+            "".to_string(),
+        );
+
+        let active_subscriptions = register_gauge_metric_instrument(
+            &rpc_meter,
+            "active_subscriptions".to_string(),
+            "A gauge to show the number of subscriptions currently open".to_string(),
+            "".to_string(),
+        );
+
+        Ok(Self {
+            calls_time,
+            calls_started,
+            calls_finished,
+            ws_sessions_opened,
+            ws_sessions_closed,
+            ws_sessions_time,
+            active_connections,
+            active_subscriptions,
+            connections_count: Default::default(),
+            subscriptions_count: Default::default(),
+        })
+    }
+
+    /// Registers a new connection, which is considered closed once the returned guard is dropped. This is only used
+    /// for reporting: the connection limit is enforced by jsonrpsee.
+    pub(crate) fn open_connection(&self) -> ActiveConnection {
+        let count = self.connections_count.fetch_add(1, Ordering::SeqCst) + 1;
+        self.active_connections.record(count, &[]);
+        ActiveConnection { metrics: self.clone() }
+    }
+
+    pub(crate) fn connections_count(&self) -> u64 {
+        self.connections_count.load(Ordering::SeqCst)
+    }
+
+    /// Registers a new subscription, which is considered closed once the returned guard is dropped.
+    pub(crate) fn open_subscription(&self) -> ActiveSubscription {
+        let count = self.subscriptions_count.fetch_add(1, Ordering::SeqCst) + 1;
+        self.active_subscriptions.record(count, &[]);
+        ActiveSubscription { metrics: self.clone() }
+    }
+
+    pub(crate) fn subscriptions_count(&self) -> u64 {
+        self.subscriptions_count.load(Ordering::SeqCst)
     }
 
     pub(crate) fn ws_connect(&self) {
@@ -133,6 +191,32 @@ impl RpcMetrics {
     }
 }
 
+/// Guard for a connection registered with [`RpcMetrics::open_connection`].
+#[derive(Debug)]
+pub(crate) struct ActiveConnection {
+    metrics: RpcMetrics,
+}
+
+impl Drop for ActiveConnection {
+    fn drop(&mut self) {
+        let count = self.metrics.connections_count.fetch_sub(1, Ordering::SeqCst) - 1;
+        self.metrics.active_connections.record(count, &[]);
+    }
+}
+
+/// Guard for a subscription registered with [`RpcMetrics::open_subscription`].
+#[derive(Debug)]
+pub(crate) struct ActiveSubscription {
+    metrics: RpcMetrics,
+}
+
+impl Drop for ActiveSubscription {
+    fn drop(&mut self) {
+        let count = self.metrics.subscriptions_count.fetch_sub(1, Ordering::SeqCst) - 1;
+        self.metrics.active_subscriptions.record(count, &[]);
+    }
+}
+
 /// Metrics with transport label.
 #[derive(Clone, Debug)]
 pub struct Metrics {
@@ -162,3 +246,39 @@ impl Metrics {
         self.inner.on_response(req, rp, self.transport_label, now)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn active_connections_tracks_open_connections() {
+        let metrics = RpcMetrics::register().unwrap();
+
+        let first = metrics.open_connection();
+        let second = metrics.open_connection();
+        assert_eq!(metrics.connections_count(), 2);
+
+        drop(first);
+        assert_eq!(metrics.connections_count(), 1);
+        let _third = metrics.open_connection();
+        assert_eq!(metrics.connections_count(), 2);
+
+        drop(second);
+        assert_eq!(metrics.connections_count(), 1);
+    }
+
+    #[test]
+    fn active_subscriptions_tracks_open_subscriptions() {
+        let metrics = RpcMetrics::register().unwrap();
+
+        let first = metrics.open_subscription();
+        let second = metrics.open_subscription();
+        assert_eq!(metrics.subscriptions_count(), 2);
+
+        drop(first);
+        assert_eq!(metrics.subscriptions_count(), 1);
+        drop(second);
+        assert_eq!(metrics.subscriptions_count(), 0);
+    }
+}
@@ -1,22 +1,30 @@
 //! JSON-RPC specific middleware.
 
 use futures::future::{BoxFuture, FutureExt};
+use futures::stream::{FuturesUnordered, StreamExt};
 use jsonrpsee::server::middleware::rpc::RpcServiceT;
-use mc_rpc::utils::ResultExt;
-use mp_chain_config::RpcVersion;
-use std::time::Instant;
+use mp_chain_config::{RpcVersion, RpcVersionError};
+use std::collections::HashMap;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Semaphore};
 
+use super::idle::{ConnectionActivity, OpenSubscription};
 pub use super::metrics::Metrics;
+use super::metrics::{ActiveSubscription, RpcMetrics};
 
 #[derive(Debug, Clone)]
 pub struct RpcMiddlewareLayerMetrics {
     metrics: Metrics,
+    slow_request_threshold: Option<Duration>,
 }
 
 impl RpcMiddlewareLayerMetrics {
-    /// Enable metrics middleware.
-    pub fn new(metrics: Metrics) -> Self {
-        Self { metrics }
+    /// Enable metrics middleware. Calls taking longer than `slow_request_threshold` are logged as warnings.
+    pub fn new(metrics: Metrics, slow_request_threshold: Option<Duration>) -> Self {
+        Self { metrics, slow_request_threshold }
     }
 
     /// Register a new websocket connection.
@@ -34,7 +42,11 @@ impl<S> tower::Layer<S> for RpcMiddlewareLayerMetrics {
     type Service = RpcMiddlewareServiceMetrics<S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        RpcMiddlewareServiceMetrics { inner, metrics: self.metrics.clone() }
+        RpcMiddlewareServiceMetrics {
+            inner,
+            metrics: self.metrics.clone(),
+            slow_request_threshold: self.slow_request_threshold,
+        }
     }
 }
 
@@ -42,6 +54,7 @@ impl<S> tower::Layer<S> for RpcMiddlewareLayerMetrics {
 pub struct RpcMiddlewareServiceMetrics<S> {
     inner: S,
     metrics: Metrics,
+    slow_request_threshold: Option<Duration>,
 }
 
 impl<'a, S> RpcServiceT<'a> for RpcMiddlewareServiceMetrics<S>
@@ -53,6 +66,7 @@ where
     fn call(&self, req: jsonrpsee::types::Request<'a>) -> Self::Future {
         let inner = self.inner.clone();
         let metrics = self.metrics.clone();
+        let slow_request_threshold = self.slow_request_threshold;
 
         async move {
             let now = std::time::Instant::now();
@@ -63,7 +77,8 @@ where
             let method = req.method_name();
             let status = rp.as_error_code().unwrap_or(200) as i64;
             let res_len = rp.as_result().len() as u64;
-            let response_time = now.elapsed().as_micros();
+            let elapsed = now.elapsed();
+            let response_time = elapsed.as_micros();
 
             tracing::info!(
                 target: "rpc_calls",
@@ -74,6 +89,15 @@ where
                 "{method} {status} {res_len} - {response_time} micros",
             );
 
+            if slow_request_threshold.is_some_and(|threshold| elapsed > threshold) {
+                tracing::warn!(
+                    target: "rpc_calls",
+                    method = method,
+                    response_time = response_time,
+                    "Slow RPC call: {method} took {elapsed:?}",
+                );
+            }
+
             metrics.on_response(&req, &rp, now);
 
             rp
@@ -82,16 +106,215 @@ where
     }
 }
 
+/// Turns a panic in a method call into an internal error response for that call only. Without this, a panicking call
+/// takes down the whole connection, and with it every other response of the batch it is part of.
+#[derive(Debug, Clone)]
+pub struct RpcMiddlewareServiceCatchPanic<S> {
+    inner: S,
+}
+
+impl<S> RpcMiddlewareServiceCatchPanic<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'a, S> RpcServiceT<'a> for RpcMiddlewareServiceCatchPanic<S>
+where
+    S: Send + Sync + Clone + RpcServiceT<'a> + 'static,
+{
+    type Future = BoxFuture<'a, jsonrpsee::MethodResponse>;
+
+    fn call(&self, req: jsonrpsee::types::Request<'a>) -> Self::Future {
+        let inner = self.inner.clone();
+
+        async move {
+            let id = req.id().into_owned();
+            let method = req.method_name().to_string();
+
+            // Synchronous methods run as soon as the call is made, so the call itself must happen inside the future.
+            match AssertUnwindSafe(async move { inner.call(req).await }).catch_unwind().await {
+                Ok(rp) => rp,
+                Err(_) => {
+                    tracing::error!(target: "rpc_calls", "RPC call {method} panicked");
+                    jsonrpsee::MethodResponse::error(
+                        id,
+                        jsonrpsee::types::ErrorObject::from(jsonrpsee::types::ErrorCode::InternalError),
+                    )
+                }
+            }
+        }
+        .boxed()
+    }
+}
+
+/// Records the calls of a connection, so that it is not closed as idle while in use. Subscriptions are tracked by
+/// [`SubscriptionTracker`] instead, as they outlive the call which opened them.
+#[derive(Debug, Clone)]
+pub struct RpcMiddlewareServiceActivity<S> {
+    inner: S,
+    activity: Arc<ConnectionActivity>,
+}
+
+impl<S> RpcMiddlewareServiceActivity<S> {
+    pub fn new(inner: S, activity: Arc<ConnectionActivity>) -> Self {
+        Self { inner, activity }
+    }
+}
+
+impl<'a, S> RpcServiceT<'a> for RpcMiddlewareServiceActivity<S>
+where
+    S: Send + Sync + Clone + RpcServiceT<'a> + 'static,
+{
+    type Future = BoxFuture<'a, jsonrpsee::MethodResponse>;
+
+    fn call(&self, req: jsonrpsee::types::Request<'a>) -> Self::Future {
+        let inner = self.inner.clone();
+        let activity = Arc::clone(&self.activity);
+
+        async move {
+            let _call = activity.call_started();
+            inner.call(req).await
+        }
+        .boxed()
+    }
+}
+
+/// Records every subscription of the websocket connections of a server in their [`ConnectionActivity`] and in the
+/// metrics, until it ends - whether it is closed by the client, by the server or along with its connection.
+///
+/// The subscription methods are wrapped once with [`SubscriptionTracker::track`]. Each websocket connection registers
+/// itself with [`SubscriptionTracker::connection`], and is served by jsonrpsee under the connection id it gets back.
+#[derive(Debug)]
+pub struct SubscriptionTracker {
+    metrics: RpcMetrics,
+    next_conn_id: AtomicU32,
+    connections: Mutex<HashMap<usize, TrackedConnection>>,
+}
+
+#[derive(Debug)]
+struct TrackedConnection {
+    activity: Arc<ConnectionActivity>,
+    opened: mpsc::UnboundedSender<OpenedSubscription>,
+}
+
+impl SubscriptionTracker {
+    pub fn new(metrics: RpcMetrics) -> Self {
+        Self { metrics, next_conn_id: Default::default(), connections: Default::default() }
+    }
+
+    /// Wraps the subscription methods of `methods`, so that the subscriptions they open are tracked.
+    pub fn track(self: &Arc<Self>, methods: &jsonrpsee::Methods) -> jsonrpsee::Methods {
+        let mut tracked = jsonrpsee::Methods::new();
+
+        for name in methods.method_names() {
+            let callback = match methods.method(name).expect("Method names come from the methods") {
+                jsonrpsee::MethodCallback::Subscription(subscribe) => {
+                    let subscribe = Arc::clone(subscribe);
+                    let this = Arc::clone(self);
+                    let callback: jsonrpsee::SubscriptionMethod<'static> =
+                        Arc::new(move |id, params, sink, mut state| {
+                            state.subscription_permit = this.opened(state.conn_id, state.subscription_permit);
+                            subscribe(id, params, sink, state)
+                        });
+                    jsonrpsee::MethodCallback::Subscription(callback)
+                }
+                callback => callback.clone(),
+            };
+            tracked.verify_and_insert(name, callback).expect("Method names are unique");
+        }
+
+        tracked
+    }
+
+    /// Registers a websocket connection. It has to be served with the returned connection id, see
+    /// [`jsonrpsee::server::TowerServiceBuilder::connection_id`].
+    pub fn connection(self: &Arc<Self>, activity: Arc<ConnectionActivity>) -> ConnectionSubscriptions {
+        let conn_id = self.next_conn_id.fetch_add(1, Ordering::Relaxed);
+        let (opened, opened_recv) = mpsc::unbounded_channel();
+        self.connections
+            .lock()
+            .expect("Poisoned lock")
+            .insert(conn_id as usize, TrackedConnection { activity, opened });
+        ConnectionSubscriptions { tracker: Arc::clone(self), conn_id, opened_recv }
+    }
+
+    /// jsonrpsee hands each subscription a permit from its connection, which is only released once the subscription
+    /// sink has been dropped. The permit is swapped for one which the connection watches, see
+    /// [`ConnectionSubscriptions::run`].
+    fn opened(&self, conn_id: usize, permit: jsonrpsee::SubscriptionPermit) -> jsonrpsee::SubscriptionPermit {
+        let connections = self.connections.lock().expect("Poisoned lock");
+        let Some(connection) = connections.get(&conn_id) else { return permit };
+
+        let released = Arc::new(Semaphore::new(1));
+        let wrapped = Arc::clone(&released).try_acquire_owned().expect("The semaphore was created with a permit");
+        // The connection is closing when nothing receives the subscription anymore: its records are dropped
+        // along with it.
+        let _ = connection.opened.send(OpenedSubscription {
+            released,
+            _permit: permit,
+            _records: (connection.activity.subscription_opened(), self.metrics.open_subscription()),
+        });
+        wrapped
+    }
+}
+
+struct OpenedSubscription {
+    released: Arc<Semaphore>,
+    _permit: jsonrpsee::SubscriptionPermit,
+    _records: (OpenSubscription, ActiveSubscription),
+}
+
+impl OpenedSubscription {
+    /// Resolves once the wrapped permit has been released. The records are dropped along with the subscription.
+    async fn ended(self) {
+        let _ = self.released.acquire().await;
+    }
+}
+
+/// Subscriptions of a single websocket connection, which are no longer tracked once this is dropped.
+pub struct ConnectionSubscriptions {
+    tracker: Arc<SubscriptionTracker>,
+    conn_id: u32,
+    opened_recv: mpsc::UnboundedReceiver<OpenedSubscription>,
+}
+
+impl ConnectionSubscriptions {
+    pub fn conn_id(&self) -> u32 {
+        self.conn_id
+    }
+
+    /// Releases the records of the subscriptions of the connection as they end. This never returns, and should be
+    /// run for as long as the connection is open.
+    pub async fn run(&mut self) {
+        let mut open = FuturesUnordered::new();
+        loop {
+            tokio::select! {
+                Some(subscription) = self.opened_recv.recv() => open.push(subscription.ended()),
+                Some(()) = open.next() => {}
+                else => std::future::pending().await,
+            }
+        }
+    }
+}
+
+impl Drop for ConnectionSubscriptions {
+    fn drop(&mut self) {
+        self.tracker.connections.lock().expect("Poisoned lock").remove(&(self.conn_id as usize));
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RpcMiddlewareServiceVersion<S> {
     inner: S,
     path: String,
     version_default: RpcVersion,
+    versions_supported: &'static [RpcVersion],
 }
 
 impl<S> RpcMiddlewareServiceVersion<S> {
-    pub fn new(inner: S, path: String, version_default: RpcVersion) -> Self {
-        Self { inner, path, version_default }
+    pub fn new(inner: S, path: String, version_default: RpcVersion, versions_supported: &'static [RpcVersion]) -> Self {
+        Self { inner, path, version_default, versions_supported }
     }
 }
 
@@ -105,25 +328,30 @@ where
         let inner = self.inner.clone();
         let path = self.path.clone();
         let version_default = self.version_default;
+        let versions_supported = self.versions_supported;
 
         async move {
             if req.method == "rpc_methods" {
                 return inner.call(req).await;
             }
 
-            let version = match RpcVersion::from_request_path(&path, version_default)
-                .map(|v| v.name())
-                .or_internal_server_error("Failed to get request path")
-            {
-                Ok(version) => version,
-                Err(_) => {
+            let version = match RpcVersion::from_request_path(&path, version_default, versions_supported) {
+                Ok(version) => version.name(),
+                Err(RpcVersionError::UnsupportedVersion) => {
                     return jsonrpsee::MethodResponse::error(
                         req.id,
-                        jsonrpsee::types::ErrorObject::owned(
-                            jsonrpsee::types::error::PARSE_ERROR_CODE,
-                            jsonrpsee::types::error::PARSE_ERROR_MSG,
-                            None::<()>,
-                        ),
+                        mc_rpc::versions::unsupported_version_error(&path, versions_supported),
+                    )
+                }
+                Err(
+                    err @ (RpcVersionError::InvalidNumber(_)
+                    | RpcVersionError::TooManyComponents(_)
+                    | RpcVersionError::InvalidPathSupplied
+                    | RpcVersionError::InvalidVersion),
+                ) => {
+                    return jsonrpsee::MethodResponse::error(
+                        req.id,
+                        mc_rpc::versions::invalid_version_error(&path, &err),
                     )
                 }
             };
@@ -148,3 +376,39 @@ where
         .boxed()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpsee::core::client::ClientT;
+    use jsonrpsee::core::params::BatchRequestBuilder;
+
+    #[tokio::test]
+    async fn batch_reports_each_element() {
+        let mut module = jsonrpsee::RpcModule::new(());
+        module.register_method("specVersion", |_, _| "0.8.0").unwrap();
+        module
+            .register_method("panics", |_, _| -> jsonrpsee::types::ErrorObjectOwned { panic!("Method panicked") })
+            .unwrap();
+
+        let rpc_middleware = jsonrpsee::server::RpcServiceBuilder::new().layer_fn(RpcMiddlewareServiceCatchPanic::new);
+        let server =
+            jsonrpsee::server::Server::builder().set_rpc_middleware(rpc_middleware).build("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", server.local_addr().unwrap());
+        let _server_handle = server.start(module);
+
+        let client = jsonrpsee::http_client::HttpClientBuilder::default().build(url).unwrap();
+        let mut batch = BatchRequestBuilder::new();
+        batch.insert("specVersion", jsonrpsee::rpc_params![]).unwrap();
+        batch.insert("panics", jsonrpsee::rpc_params![]).unwrap();
+        batch.insert("doesNotExist", jsonrpsee::rpc_params![]).unwrap();
+
+        let responses = client.batch_request::<String>(batch).await.unwrap();
+        let responses: Vec<_> = responses.into_iter().collect();
+
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0].as_ref().unwrap(), "0.8.0");
+        assert_eq!(responses[1].as_ref().unwrap_err().code(), jsonrpsee::types::error::INTERNAL_ERROR_CODE);
+        assert_eq!(responses[2].as_ref().unwrap_err().code(), jsonrpsee::types::error::METHOD_NOT_FOUND_CODE);
+    }
+}
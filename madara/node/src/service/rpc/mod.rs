@@ -1,17 +1,52 @@
 use self::server::rpc_api_build;
 use crate::{cli::RpcParams, submit_tx::MakeSubmitTransactionSwitch};
 use jsonrpsee::server::ServerHandle;
+use jsonrpsee::RpcModule;
 use mc_db::MadaraBackend;
 use mc_rpc::{rpc_api_admin, rpc_api_user, Starknet};
+use mc_sync::SyncPhaseReceiver;
 use metrics::RpcMetrics;
 use mp_utils::service::{MadaraServiceId, PowerOfTwo, Service, ServiceId, ServiceRunner};
-use server::{start_server, ServerConfig};
+use server::{start_server, BindError, ServerConfig};
 use std::sync::Arc;
+use std::time::Duration;
 
+mod idle;
 mod metrics;
 mod middleware;
 mod server;
 
+/// Methods which must only ever be served by the admin RPC endpoint, as `namespace_method` without their version.
+/// Any new sensitive admin method should be added here.
+const ADMIN_ONLY_METHODS: &[&str] =
+    &["madara_addDeclareV0Transaction", "madara_checkpoint", "madara_shutdown", "madara_pulse", "madara_service"];
+
+/// Makes sure none of the [`ADMIN_ONLY_METHODS`] ended up registered on the user RPC api, in any version.
+fn check_no_admin_methods<M>(rpc_api: &RpcModule<M>) -> anyhow::Result<()> {
+    let leaked = rpc_api
+        .method_names()
+        .filter(|name| {
+            let split = name.split('_').collect::<Vec<_>>();
+            let (namespace, method) = (split[0], split[split.len() - 1]);
+            ADMIN_ONLY_METHODS.contains(&format!("{namespace}_{method}").as_str())
+        })
+        .collect::<Vec<_>>();
+    anyhow::ensure!(leaked.is_empty(), "Admin-only methods are exposed on the user RPC api: {}", leaked.join(", "));
+    Ok(())
+}
+
+/// Methods called for their effect rather than their result, as `namespace_method` without their version. With
+/// `--rpc-lenient-ids`, calls to these methods without an id are still treated as notifications and left unanswered.
+const NOTIFICATION_METHODS: &[&str] = &[
+    "starknet_addInvokeTransaction",
+    "starknet_addDeclareTransaction",
+    "starknet_addDeployAccountTransaction",
+    "madara_addDeclareV0Transaction",
+    "madara_checkpoint",
+    "madara_shutdown",
+    "madara_service",
+];
+
 #[derive(Clone)]
 pub enum RpcType {
     User,
@@ -24,6 +59,7 @@ pub struct RpcService {
     submit_tx_provider: MakeSubmitTransactionSwitch,
     server_handle: Option<ServerHandle>,
     rpc_type: RpcType,
+    sync_phase_recv: Option<SyncPhaseReceiver>,
 }
 
 impl RpcService {
@@ -32,7 +68,14 @@ impl RpcService {
         backend: Arc<MadaraBackend>,
         submit_tx_provider: MakeSubmitTransactionSwitch,
     ) -> Self {
-        Self { config, backend, submit_tx_provider, server_handle: None, rpc_type: RpcType::User }
+        Self {
+            config,
+            backend,
+            submit_tx_provider,
+            server_handle: None,
+            rpc_type: RpcType::User,
+            sync_phase_recv: None,
+        }
     }
 
     pub fn admin(
@@ -40,7 +83,19 @@ impl RpcService {
         backend: Arc<MadaraBackend>,
         submit_tx_provider: MakeSubmitTransactionSwitch,
     ) -> Self {
-        Self { config, backend, submit_tx_provider, server_handle: None, rpc_type: RpcType::Admin }
+        Self {
+            config,
+            backend,
+            submit_tx_provider,
+            server_handle: None,
+            rpc_type: RpcType::Admin,
+            sync_phase_recv: None,
+        }
+    }
+
+    /// Answer `/ready` from the phase of the sync service, when it is running.
+    pub fn with_sync_phase_recv(self, sync_phase_recv: Option<SyncPhaseReceiver>) -> Self {
+        Self { sync_phase_recv, ..self }
     }
 }
 
@@ -51,6 +106,7 @@ impl Service for RpcService {
         let backend = Arc::clone(&self.backend);
         let submit_tx_provider = self.submit_tx_provider.clone();
         let rpc_type = self.rpc_type.clone();
+        let sync_phase_recv = self.sync_phase_recv.clone();
 
         let (stop_handle, server_handle) = jsonrpsee::server::stop_channel();
 
@@ -59,29 +115,39 @@ impl Service for RpcService {
         runner.service_loop(move |ctx| async move {
             let submit_tx = Arc::new(submit_tx_provider.make(ctx.clone()));
 
-            let starknet = Starknet::new(backend.clone(), submit_tx, config.storage_proof_config(), ctx.clone());
+            let starknet = Starknet::new(backend.clone(), submit_tx, config.storage_proof_config(), ctx.clone())
+                .with_casm_cache_size(config.rpc_casm_cache_size);
             let metrics = RpcMetrics::register()?;
 
             let server_config = {
-                let (name, addr, api_rpc, rpc_version_default) = match rpc_type {
+                let (name, addrs, uds_path, api_rpc, rpc_version_default, rpc_versions_supported) = match rpc_type {
                     RpcType::User => (
                         "JSON-RPC".to_string(),
-                        config.addr_user(),
-                        rpc_api_user(&starknet)?,
+                        config.addrs_user(),
+                        config.rpc_uds_path.clone(),
+                        {
+                            let rpc_api = rpc_api_user(&starknet)?;
+                            check_no_admin_methods(&rpc_api)?;
+                            rpc_api
+                        },
                         mp_chain_config::RpcVersion::RPC_VERSION_LATEST,
+                        mp_chain_config::SUPPORTED_RPC_VERSIONS_USER,
                     ),
                     RpcType::Admin => (
                         "JSON-RPC (Admin)".to_string(),
-                        config.addr_admin(),
+                        vec![config.addr_admin()],
+                        None,
                         rpc_api_admin(&starknet)?,
                         mp_chain_config::RpcVersion::RPC_VERSION_LATEST_ADMIN,
+                        mp_chain_config::SUPPORTED_RPC_VERSIONS_ADMIN,
                     ),
                 };
                 let methods = rpc_api_build("rpc", api_rpc).into();
 
                 ServerConfig {
                     name,
-                    addr,
+                    addrs,
+                    uds_path,
                     batch_config: config.batch_config(),
                     max_connections: config.rpc_max_connections,
                     max_payload_in_mib: config.rpc_max_request_size,
@@ -90,12 +156,27 @@ impl Service for RpcService {
                     message_buffer_capacity: config.rpc_message_buffer_capacity_per_connection,
                     methods,
                     metrics,
+                    slow_request_threshold: config.rpc_slow_request_ms.map(Duration::from_millis),
+                    connection_idle_timeout: config.rpc_connection_idle_timeout_ms.map(Duration::from_millis),
+                    compression: config.rpc_compression,
+                    lenient_ids: config.rpc_lenient_ids,
+                    notification_methods: NOTIFICATION_METHODS,
                     cors: config.cors(),
                     rpc_version_default,
+                    rpc_versions_supported,
+                    sync_phase_recv,
                 }
             };
 
-            start_server(server_config, ctx.clone(), stop_handle, Arc::new(starknet)).await?;
+            let name = server_config.name.clone();
+            match start_server(server_config, ctx.clone(), stop_handle, Arc::new(starknet)).await {
+                Err(err)
+                    if matches!(rpc_type, RpcType::Admin) && config.rpc_admin_optional && err.is::<BindError>() =>
+                {
+                    tracing::warn!("⚠️ Could not start the {name} server, continuing without it: {err:#}");
+                }
+                res => res?,
+            }
 
             anyhow::Ok(())
         });
@@ -113,3 +194,31 @@ impl ServiceId for RpcService {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module_with(methods: &[&'static str]) -> RpcModule<()> {
+        let mut module = RpcModule::new(());
+        for method in methods {
+            module.register_method(*method, |_, _| ()).unwrap();
+        }
+        module
+    }
+
+    #[test]
+    fn user_api_without_admin_methods() {
+        let module = module_with(&["starknet_V0_7_1_blockNumber", "starknet_V0_8_0_unsubscribe", "madara_ping"]);
+        check_no_admin_methods(&module).unwrap();
+    }
+
+    #[test]
+    fn user_api_with_admin_methods() {
+        for method in ["madara_V0_1_0_shutdown", "madara_V0_2_0_addDeclareV0Transaction", "madara_service"] {
+            let module = module_with(&["starknet_V0_7_1_blockNumber", method]);
+            let err = check_no_admin_methods(&module).unwrap_err();
+            assert!(err.to_string().contains(method), "{err}");
+        }
+    }
+}
@@ -1,17 +1,26 @@
 #![allow(clippy::declare_interior_mutable_const)]
 #![allow(clippy::borrow_interior_mutable_const)]
 
+use super::idle::{ConnectionActivity, IdleTimeoutAccept, IdleTimeoutStream};
 use super::metrics::RpcMetrics;
-use super::middleware::{Metrics, RpcMiddlewareLayerMetrics};
-use crate::service::rpc::middleware::RpcMiddlewareServiceVersion;
+use super::middleware::{Metrics, RpcMiddlewareLayerMetrics, SubscriptionTracker};
+use crate::service::rpc::middleware::{
+    RpcMiddlewareServiceActivity, RpcMiddlewareServiceCatchPanic, RpcMiddlewareServiceVersion,
+};
 use anyhow::Context;
+use futures::future::{BoxFuture, FutureExt};
 use mc_rpc::versions::user::v0_7_1::methods::read::syncing::syncing;
 use mc_rpc::Starknet;
+use mc_sync::{SyncPhase, SyncPhaseReceiver};
 use mp_rpc::SyncingStatus;
 use mp_utils::service::ServiceContext;
 use std::convert::Infallible;
 use std::net::SocketAddr;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
 use std::time::Duration;
 use tower::Service;
 
@@ -22,67 +31,144 @@ const MiB: u32 = 1024 * 1024;
 #[derive(Debug, Clone)]
 pub struct ServerConfig {
     pub name: String,
-    pub addr: SocketAddr,
+    /// Addresses to listen on, all serving the same methods. This is how a node can listen on both IPv4 and IPv6.
+    pub addrs: Vec<SocketAddr>,
+    /// Also serve the RPC on this unix domain socket.
+    pub uds_path: Option<PathBuf>,
     pub cors: Option<Vec<String>>,
     pub rpc_version_default: mp_chain_config::RpcVersion,
+    /// Versions served by this server. Requests targeting any other version are rejected.
+    pub rpc_versions_supported: &'static [mp_chain_config::RpcVersion],
     pub max_connections: u32,
     pub max_subs_per_conn: u32,
     pub max_payload_in_mib: u32,
     pub max_payload_out_mib: u32,
     pub metrics: RpcMetrics,
+    /// Calls taking longer than this are logged as warnings.
+    pub slow_request_threshold: Option<Duration>,
+    /// Connections without any call in flight or open subscription are closed after being idle for this long.
+    pub connection_idle_timeout: Option<Duration>,
+    /// Compress HTTP responses according to the `Accept-Encoding` request header.
+    pub compression: bool,
+    /// Answer calls without an id with a `null` id, instead of treating them as notifications.
+    pub lenient_ids: bool,
+    /// Methods called for their effect rather than their result, as `namespace_method` without their version. Calls to
+    /// them without an id are still treated as notifications with `lenient_ids`.
+    pub notification_methods: &'static [&'static str],
     pub message_buffer_capacity: u32,
     pub methods: jsonrpsee::Methods,
     /// Batch request config.
     pub batch_config: jsonrpsee::server::BatchRequestConfig,
+    /// Phase of the sync service, used to answer `/ready`. Without it, readiness falls back to the `syncing` status
+    /// of the database.
+    pub sync_phase_recv: Option<SyncPhaseReceiver>,
+}
+
+/// The server could not listen on one of its addresses, for example because it is already in use.
+#[derive(Debug, thiserror::Error)]
+#[error("Binding TCP listener to address: {addr}")]
+pub struct BindError {
+    addr: SocketAddr,
+    #[source]
+    source: std::io::Error,
 }
 
 #[derive(Debug, Clone)]
 struct PerConnection<RpcMiddleware, HttpMiddleware> {
     methods: jsonrpsee::Methods,
+    subscriptions: Arc<SubscriptionTracker>,
     stop_handle: jsonrpsee::server::StopHandle,
     metrics: RpcMetrics,
     service_builder: jsonrpsee::server::TowerServiceBuilder<RpcMiddleware, HttpMiddleware>,
 }
 
-/// Start RPC server listening on given address.
+/// Listeners of an RPC server. All the addresses are bound before serving any of them, so that the server does not
+/// run partially.
+struct ServerListeners {
+    tcp: Vec<(tokio::net::TcpListener, SocketAddr)>,
+    uds: Option<(tokio::net::UnixListener, UnixSocketFile)>,
+}
+
+impl ServerListeners {
+    async fn bind(config: &ServerConfig) -> anyhow::Result<Self> {
+        let mut tcp = Vec::with_capacity(config.addrs.len());
+        for &addr in &config.addrs {
+            let listener = tokio::net::TcpListener::bind(addr).await.map_err(|source| BindError { addr, source })?;
+            let local_addr =
+                listener.local_addr().context("Failed to retrieve local address after binding TCP listener")?;
+            tcp.push((listener, local_addr));
+        }
+
+        let uds = match &config.uds_path {
+            Some(path) => {
+                remove_stale_socket(path)?;
+                let listener = tokio::net::UnixListener::bind(path)
+                    .with_context(|| format!("Binding unix listener to path: {}", path.display()))?;
+                Some((listener, UnixSocketFile(path.clone())))
+            }
+            None => None,
+        };
+
+        Ok(Self { tcp, uds })
+    }
+
+    #[cfg(test)]
+    fn local_addrs(&self) -> Vec<SocketAddr> {
+        self.tcp.iter().map(|(_, local_addr)| *local_addr).collect()
+    }
+}
+
+/// Start RPC server listening on given addresses.
 ///
 /// This future will complete once the server has been shutdown.
 pub async fn start_server(
     config: ServerConfig,
-    mut ctx: ServiceContext,
+    ctx: ServiceContext,
+    stop_handle: jsonrpsee::server::StopHandle,
+    starknet: Arc<Starknet>,
+) -> anyhow::Result<()> {
+    let listeners = ServerListeners::bind(&config).await?;
+    serve(config, listeners, ctx, stop_handle, starknet).await
+}
+
+async fn serve(
+    config: ServerConfig,
+    listeners: ServerListeners,
+    ctx: ServiceContext,
     stop_handle: jsonrpsee::server::StopHandle,
     starknet: Arc<Starknet>,
 ) -> anyhow::Result<()> {
     let ServerConfig {
         name,
-        addr,
+        addrs: _,
+        uds_path,
         cors,
         rpc_version_default,
+        rpc_versions_supported,
         max_connections,
         max_subs_per_conn,
         max_payload_in_mib,
         max_payload_out_mib,
         metrics,
+        slow_request_threshold,
+        connection_idle_timeout,
+        compression,
+        lenient_ids,
+        notification_methods,
         message_buffer_capacity,
         methods,
         batch_config,
+        sync_phase_recv,
     } = config;
 
-    let listener = tokio::net::TcpListener::bind(addr)
-        .await
-        .with_context(|| format!("Binding TCP listener to address: {addr}"))?;
-    let local_addr = listener.local_addr().context("Failed to retrieve local address after binding TCP listener")?;
-
     let ping_config = jsonrpsee::server::PingConfig::new()
         .ping_interval(Duration::from_secs(30))
         .inactive_limit(Duration::from_secs(60))
         .max_failures(3);
 
-    let http_middleware = tower::ServiceBuilder::new()
-        .option_layer(host_filtering(cors.is_some(), local_addr))
-        .layer(try_into_cors(cors.as_ref())?);
-
-    let builder = jsonrpsee::server::Server::builder()
+    // The listeners all share this service builder, and with it a single connection guard: `max_connections` applies
+    // to the server as a whole and not to each of its addresses.
+    let shared_service_builder = jsonrpsee::server::Server::builder()
         .max_request_body_size(max_payload_in_mib.saturating_mul(MiB))
         .max_response_body_size(max_payload_out_mib.saturating_mul(MiB))
         .max_connections(max_connections)
@@ -90,104 +176,381 @@ pub async fn start_server(
         .enable_ws_ping(ping_config)
         .set_message_buffer_capacity(message_buffer_capacity)
         .set_batch_request_config(batch_config)
-        .set_http_middleware(http_middleware)
-        .set_id_provider(jsonrpsee::server::RandomStringIdProvider::new(16));
+        .set_id_provider(jsonrpsee::server::RandomStringIdProvider::new(16))
+        .to_service_builder();
 
-    let cfg = PerConnection {
-        methods,
+    // Only the host filter differs between listeners.
+    let service_builder = |host_filter| -> anyhow::Result<_> {
+        let http_middleware = tower::ServiceBuilder::new()
+            .option_layer(host_filter)
+            .layer(try_into_cors(cors.as_ref())?)
+            .option_layer(compression.then(compression_layer))
+            .option_layer(
+                lenient_ids.then(|| LenientIdsLayer::new(max_payload_in_mib.saturating_mul(MiB), notification_methods)),
+            );
+
+        Ok(shared_service_builder.clone().set_http_middleware(http_middleware))
+    };
+
+    // Requests over the unix socket do not come with a meaningful host header.
+    let subscriptions = Arc::new(SubscriptionTracker::new(metrics.clone()));
+    let uds_cfg = PerConnection {
+        methods: subscriptions.track(&methods),
+        subscriptions,
         stop_handle: stop_handle.clone(),
         metrics,
-        service_builder: builder.to_service_builder(),
+        service_builder: service_builder(None)?,
     };
     let ctx1 = ctx.clone();
 
-    let make_service = hyper::service::make_service_fn(move |_| {
-        let cfg = cfg.clone();
+    // Creates the service handling the requests of a single connection.
+    let connection_service = move |cfg: PerConnection<_, _>, activity: Arc<ConnectionActivity>| {
         let ctx1 = ctx1.clone();
         let starknet = Arc::clone(&starknet);
+        let sync_phase_recv = sync_phase_recv.clone();
 
-        async move {
-            let cfg = cfg.clone();
+        // The connection is tracked until the hyper service is dropped, or until the websocket session it was
+        // upgraded to is closed.
+        let connection = Arc::new(cfg.metrics.open_connection());
+
+        hyper::service::service_fn(move |req| {
+            let PerConnection { service_builder, metrics, stop_handle, methods, subscriptions } = cfg.clone();
+            let ctx1 = ctx1.clone();
             let starknet = Arc::clone(&starknet);
+            let sync_phase_recv = sync_phase_recv.clone();
+            let connection = connection.clone();
+            let activity = Arc::clone(&activity);
+            activity.touch();
 
-            Ok::<_, Infallible>(hyper::service::service_fn(move |req| {
-                let PerConnection { service_builder, metrics, stop_handle, methods } = cfg.clone();
-                let ctx1 = ctx1.clone();
-                let starknet = Arc::clone(&starknet);
-
-                let is_websocket = jsonrpsee::server::ws::is_upgrade_request(&req);
-                let transport_label = if is_websocket { "ws" } else { "http" };
-                let path = req.uri().path().to_string();
-                let metrics_layer = RpcMiddlewareLayerMetrics::new(Metrics::new(metrics, transport_label));
-
-                let rpc_middleware = jsonrpsee::server::RpcServiceBuilder::new()
-                    .layer_fn(move |service| {
-                        RpcMiddlewareServiceVersion::new(service, path.clone(), rpc_version_default)
-                    })
-                    .layer(metrics_layer.clone());
-
-                let mut svc = service_builder.set_rpc_middleware(rpc_middleware).build(methods, stop_handle);
-
-                async move {
-                    if ctx1.is_cancelled() {
-                        Ok(hyper::Response::builder()
-                            .status(hyper::StatusCode::GONE)
-                            .body(hyper::Body::from("GONE"))?)
-                    } else if req.uri().path() == "/health" {
-                        Ok(hyper::Response::builder().status(hyper::StatusCode::OK).body(hyper::Body::from("OK"))?)
-                    } else if req.uri().path() == "/ready" {
-                        let sync_status = syncing(&starknet).await;
-                        match sync_status {
-                            Ok(sync_status) => match sync_status {
-                                SyncingStatus::Syncing(_) => Ok(hyper::Response::builder()
-                                    .status(hyper::StatusCode::SERVICE_UNAVAILABLE)
-                                    .body(hyper::Body::from("SYNCING"))?),
-                                SyncingStatus::NotSyncing => Ok(hyper::Response::builder()
-                                    .status(hyper::StatusCode::OK)
-                                    .body(hyper::Body::from("OK"))?),
-                            },
-                            Err(_) => Ok(hyper::Response::builder()
-                                .status(hyper::StatusCode::INTERNAL_SERVER_ERROR)
-                                .body(hyper::Body::from("INTERNAL_SERVER_ERROR"))?),
-                        }
-                    } else {
-                        if is_websocket {
-                            // Utilize the session close future to know when the actual WebSocket
-                            // session was closed.
-                            let on_disconnect = svc.on_session_closed();
-
-                            // Spawn a task to handle when the connection is closed.
-                            tokio::spawn(async move {
-                                let now = std::time::Instant::now();
-                                metrics_layer.ws_connect();
-                                on_disconnect.await;
-                                metrics_layer.ws_disconnect(now);
-                            });
-                        }
+            let is_websocket = jsonrpsee::server::ws::is_upgrade_request(&req);
+            let transport_label = if is_websocket { "ws" } else { "http" };
+            let path = req.uri().path().to_string();
+            let metrics_layer =
+                RpcMiddlewareLayerMetrics::new(Metrics::new(metrics.clone(), transport_label), slow_request_threshold);
+
+            let rpc_middleware = jsonrpsee::server::RpcServiceBuilder::new()
+                .layer_fn(move |service| {
+                    RpcMiddlewareServiceVersion::new(service, path.clone(), rpc_version_default, rpc_versions_supported)
+                })
+                .layer(metrics_layer.clone())
+                .layer_fn({
+                    let activity = Arc::clone(&activity);
+                    move |service| RpcMiddlewareServiceActivity::new(service, Arc::clone(&activity))
+                })
+                .layer_fn(RpcMiddlewareServiceCatchPanic::new);
+
+            async move {
+                if ctx1.is_cancelled() {
+                    Ok(hyper::Response::builder().status(hyper::StatusCode::GONE).body(hyper::Body::from("GONE"))?)
+                } else if req.uri().path() == "/health" {
+                    Ok(hyper::Response::builder().status(hyper::StatusCode::OK).body(hyper::Body::from("OK"))?)
+                } else if req.uri().path() == "/ready" {
+                    let at_tip = match sync_phase_recv {
+                        Some(sync_phase_recv) => Ok(*sync_phase_recv.borrow() == Some(SyncPhase::AtTip)),
+                        None => syncing(&starknet).await.map(|status| matches!(status, SyncingStatus::NotSyncing)),
+                    };
+                    match at_tip {
+                        Ok(false) => Ok(hyper::Response::builder()
+                            .status(hyper::StatusCode::SERVICE_UNAVAILABLE)
+                            .body(hyper::Body::from("SYNCING"))?),
+                        Ok(true) => Ok(hyper::Response::builder()
+                            .status(hyper::StatusCode::OK)
+                            .body(hyper::Body::from("OK"))?),
+                        Err(_) => Ok(hyper::Response::builder()
+                            .status(hyper::StatusCode::INTERNAL_SERVER_ERROR)
+                            .body(hyper::Body::from("INTERNAL_SERVER_ERROR"))?),
+                    }
+                } else if is_websocket {
+                    // The session has its own stop handle, so that it can be closed on its own with a close frame.
+                    let (session_stop_handle, session_handle) = jsonrpsee::server::stop_channel();
+                    let mut subscriptions = subscriptions.connection(Arc::clone(&activity));
+                    let mut svc = service_builder
+                        .connection_id(subscriptions.conn_id())
+                        .set_rpc_middleware(rpc_middleware)
+                        .build(methods, session_stop_handle);
+
+                    // Utilize the session close future to know when the actual WebSocket
+                    // session was closed.
+                    let on_disconnect = svc.on_session_closed();
+                    let res = svc.call(req).await?;
 
-                        svc.call(req).await
+                    if res.status() == hyper::StatusCode::SWITCHING_PROTOCOLS {
+                        activity.upgraded_to_websocket();
+
+                        // Spawn a task to close the session once the server stops or the connection is idle, and to
+                        // handle when the connection is closed.
+                        tokio::spawn(async move {
+                            let now = std::time::Instant::now();
+                            metrics_layer.ws_connect();
+
+                            let idle = async {
+                                match connection_idle_timeout {
+                                    Some(timeout) => activity.idle(timeout).await,
+                                    None => std::future::pending().await,
+                                }
+                            };
+                            tokio::pin!(on_disconnect);
+                            tokio::select! {
+                                _ = &mut on_disconnect => {}
+                                _ = stop_handle.shutdown() => {}
+                                _ = idle => tracing::debug!("Closing idle RPC websocket session"),
+                                _ = subscriptions.run() => {}
+                            }
+                            let _ = session_handle.stop();
+
+                            on_disconnect.await;
+                            metrics_layer.ws_disconnect(now);
+                            drop((subscriptions, connection));
+                        });
                     }
+
+                    Ok(res)
+                } else {
+                    let mut svc = service_builder.set_rpc_middleware(rpc_middleware).build(methods, stop_handle);
+                    svc.call(req).await
                 }
-            }))
+            }
+        })
+    };
+
+    let mut servers = Vec::with_capacity(listeners.tcp.len() + 1);
+
+    for (listener, local_addr) in listeners.tcp {
+        // Each listener only accepts its own address as host.
+        let cfg = PerConnection {
+            service_builder: service_builder(host_filtering(cors.is_some(), local_addr))?,
+            ..uds_cfg.clone()
+        };
+        let connection_service = connection_service.clone();
+        let make_service = hyper::service::make_service_fn(move |conn: &IdleTimeoutStream<_>| {
+            let service = connection_service(cfg.clone(), conn.activity());
+            async move { Ok::<_, Infallible>(service) }
+        });
+
+        let incoming = hyper::server::conn::AddrIncoming::from_listener(listener)
+            .with_context(|| format!("Creating hyper server at: {local_addr}"))?;
+
+        tracing::info!(
+            "📱 Running {name} server at {} (allowed origins={})",
+            local_addr.to_string(),
+            format_cors(cors.as_ref())
+        );
+
+        let mut ctx = ctx.clone();
+        let stop_handle = stop_handle.clone();
+        servers.push(
+            hyper::Server::builder(IdleTimeoutAccept::new(incoming, connection_idle_timeout))
+                .serve(make_service)
+                .with_graceful_shutdown(async move {
+                    ctx.run_until_cancelled(stop_handle.shutdown()).await;
+                })
+                .boxed(),
+        );
+    }
+
+    let (uds_listener, uds_socket_file) = listeners.uds.unzip();
+    if let (Some(listener), Some(path)) = (uds_listener, uds_path) {
+        let make_service = hyper::service::make_service_fn(move |conn: &IdleTimeoutStream<_>| {
+            let service = connection_service(uds_cfg.clone(), conn.activity());
+            async move { Ok::<_, Infallible>(service) }
+        });
+
+        tracing::info!("📱 Running {name} server at {}", path.display());
+
+        let mut ctx = ctx.clone();
+        let stop_handle = stop_handle.clone();
+        servers.push(
+            hyper::Server::builder(IdleTimeoutAccept::new(UnixAccept(listener), connection_idle_timeout))
+                .serve(make_service)
+                .with_graceful_shutdown(async move {
+                    ctx.run_until_cancelled(stop_handle.shutdown()).await;
+                })
+                .boxed(),
+        );
+    }
+
+    let res = futures::future::try_join_all(servers).await.context("Running rpc server");
+    // No one is listening on the socket anymore.
+    drop(uds_socket_file);
+    res?;
+
+    Ok(())
+}
+
+/// Removes the socket left behind at `path` by a previous run, if any. Anything else found there is left untouched and
+/// is an error, as the path is most likely wrong.
+fn remove_stale_socket(path: &Path) -> anyhow::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            std::fs::remove_file(path).with_context(|| format!("Removing stale unix socket: {}", path.display()))
         }
-    });
+        Ok(_) => anyhow::bail!("Refusing to replace {}: it is not a unix socket", path.display()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err).with_context(|| format!("Reading metadata of unix socket path: {}", path.display())),
+    }
+}
 
-    let server = hyper::Server::from_tcp(listener.into_std()?)
-        .with_context(|| format!("Creating hyper server at: {addr}"))?
-        .serve(make_service);
+/// Path of a unix socket the server listens on, removed once dropped.
+struct UnixSocketFile(PathBuf);
 
-    tracing::info!(
-        "📱 Running {name} server at {} (allowed origins={})",
-        local_addr.to_string(),
-        format_cors(cors.as_ref())
-    );
+impl Drop for UnixSocketFile {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.0) {
+            tracing::debug!("Failed to remove unix socket {}: {err:#}", self.0.display());
+        }
+    }
+}
 
-    server
-        .with_graceful_shutdown(async {
-            ctx.run_until_cancelled(stop_handle.shutdown()).await;
-        })
-        .await
-        .context("Running rpc server")
+/// Accepts the connections made to a unix domain socket.
+struct UnixAccept(tokio::net::UnixListener);
+
+impl hyper::server::accept::Accept for UnixAccept {
+    type Conn = tokio::net::UnixStream;
+    type Error = std::io::Error;
+
+    fn poll_accept(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+        self.0.poll_accept(cx).map(|res| Some(res.map(|(stream, _addr)| stream)))
+    }
+}
+
+/// Compresses http responses. jsonrpsee expects its http middleware to return a [`hyper::Body`], so the compressed body
+/// is buffered back into one - responses are already fully buffered by jsonrpsee anyway.
+fn compression_layer() -> tower::ServiceBuilder<
+    tower::layer::util::Stack<
+        tower_http::compression::CompressionLayer,
+        tower::layer::util::Stack<tower::util::AndThenLayer<BufferCompressedBody>, tower::layer::util::Identity>,
+    >,
+> {
+    tower::ServiceBuilder::new()
+        .and_then(buffer_compressed_body as BufferCompressedBody)
+        .layer(tower_http::compression::CompressionLayer::new())
+}
+
+type BufferCompressedBody = fn(
+    hyper::Response<tower_http::compression::CompressionBody<hyper::Body>>,
+) -> BoxFuture<'static, Result<hyper::Response<hyper::Body>, BoxError>>;
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+fn buffer_compressed_body(
+    res: hyper::Response<tower_http::compression::CompressionBody<hyper::Body>>,
+) -> BoxFuture<'static, Result<hyper::Response<hyper::Body>, BoxError>> {
+    async move {
+        let (parts, body) = res.into_parts();
+        let body = hyper::body::to_bytes(body).await?;
+        Ok(hyper::Response::from_parts(parts, hyper::Body::from(body)))
+    }
+    .boxed()
+}
+
+/// Gives a `null` id to the calls which are missing one, so that they are answered instead of being treated as
+/// notifications. Calls to one of the `notification_methods` are left untouched, they are genuine notifications.
+///
+/// Only json http request bodies are rewritten. Any other request is passed on as it is, so that jsonrpsee can reject it
+/// before its body is read. Websocket messages are left as they are too.
+#[derive(Debug, Clone, Copy)]
+struct LenientIdsLayer {
+    max_request_body_size: u32,
+    notification_methods: &'static [&'static str],
+}
+
+impl LenientIdsLayer {
+    fn new(max_request_body_size: u32, notification_methods: &'static [&'static str]) -> Self {
+        Self { max_request_body_size, notification_methods }
+    }
+}
+
+impl<S> tower::Layer<S> for LenientIdsLayer {
+    type Service = LenientIds<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LenientIds {
+            inner,
+            max_request_body_size: self.max_request_body_size,
+            notification_methods: self.notification_methods,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LenientIds<S> {
+    inner: S,
+    max_request_body_size: u32,
+    notification_methods: &'static [&'static str],
+}
+
+impl<S> Service<hyper::Request<hyper::Body>> for LenientIds<S>
+where
+    S: Service<hyper::Request<hyper::Body>, Response = hyper::Response<hyper::Body>> + Clone + Send + 'static,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+{
+    type Response = hyper::Response<hyper::Body>;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: hyper::Request<hyper::Body>) -> Self::Future {
+        // The service which was polled ready is the one to call, leave a fresh clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let max_request_body_size = self.max_request_body_size;
+        let notification_methods = self.notification_methods;
+
+        async move {
+            if req.method() != hyper::Method::POST || !jsonrpsee::server::http::content_type_is_json(&req) {
+                return inner.call(req).await.map_err(Into::into);
+            }
+
+            let (mut parts, mut body) = req.into_parts();
+            let mut bytes = Vec::new();
+            while let Some(chunk) = hyper::body::HttpBody::data(&mut body).await {
+                let chunk = chunk?;
+                if bytes.len() + chunk.len() > max_request_body_size as usize {
+                    return Ok(jsonrpsee::server::http::response::too_large(max_request_body_size));
+                }
+                bytes.extend_from_slice(&chunk);
+            }
+
+            if let Some(rewritten) = add_missing_ids(&bytes, notification_methods) {
+                bytes = rewritten;
+                parts.headers.insert(hyper::header::CONTENT_LENGTH, bytes.len().into());
+            }
+            inner.call(hyper::Request::from_parts(parts, bytes.into())).await.map_err(Into::into)
+        }
+        .boxed()
+    }
+}
+
+/// Adds a `null` id to the calls of a request body which do not have one, be it a single call or a batch, unless they
+/// are calls to one of the `notification_methods`. Returns `None` when the body is left untouched, including when it
+/// is not valid json: jsonrpsee reports the error then.
+fn add_missing_ids(body: &[u8], notification_methods: &[&str]) -> Option<Vec<u8>> {
+    let add_missing_id = |call: &mut serde_json::Value| {
+        let Some(call) = call.as_object_mut() else { return false };
+        if call.contains_key("id") {
+            return false;
+        }
+        let Some(method) = call.get("method").and_then(|method| method.as_str()) else { return false };
+        // Methods may be called with their version, as `namespace_version_method`.
+        let split = method.split('_').collect::<Vec<_>>();
+        if notification_methods.contains(&format!("{}_{}", split[0], split[split.len() - 1]).as_str()) {
+            return false;
+        }
+        // Logged at debug level only, as clients could otherwise flood the logs.
+        tracing::debug!(target: "rpc_calls", "Call to {method} has no id, answering it with a null id");
+        call.insert("id".into(), serde_json::Value::Null);
+        true
+    };
+
+    let mut request: serde_json::Value = serde_json::from_slice(body).ok()?;
+    let added = match &mut request {
+        serde_json::Value::Array(calls) => calls.iter_mut().fold(false, |added, call| add_missing_id(call) | added),
+        call => add_missing_id(call),
+    };
+    added.then(|| serde_json::to_vec(&request).expect("Serializing a json value is infallible"))
 }
 
 // Copied from https://github.com/paritytech/polkadot-sdk/blob/a0aefc6b233ace0a82a8631d67b6854e6aeb014b/substrate/client/rpc-servers/src/utils.rs#L192
@@ -276,3 +639,511 @@ pub(crate) fn format_cors(maybe_cors: Option<&Vec<String>>) -> String {
         format!("{:?}", ["*"])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpsee::core::client::ClientT;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn test_starknet() -> Arc<Starknet> {
+        let backend = mc_db::MadaraBackend::open_for_testing(Arc::new(mp_chain_config::ChainConfig::madara_test()));
+        let submit_tx = Arc::new(mc_gateway_client::GatewayProvider::starknet_alpha_mainnet());
+        Arc::new(Starknet::new(backend, submit_tx, Default::default(), ServiceContext::new()))
+    }
+
+    /// Configuration of a server listening on a random local port, answering `madara_echo`, `madara_large` and
+    /// `madara_notify` calls. `madara_subscribeTicks` sends as many ticks as requested, one every 100ms.
+    fn test_config() -> ServerConfig {
+        let mut module = jsonrpsee::RpcModule::new(());
+        module.register_method("madara_V0_8_0_echo", |_, _| "madara").unwrap();
+        module.register_method("madara_V0_8_0_large", |_, _| "madara".repeat(10_000)).unwrap();
+        module.register_method("madara_V0_8_0_notify", |_, _| "notified").unwrap();
+        module
+            .register_subscription(
+                "madara_V0_8_0_subscribeTicks",
+                "madara_tick",
+                "madara_V0_8_0_unsubscribeTicks",
+                |params, pending, _| async move {
+                    let count: u64 = params.one()?;
+                    let sink = pending.accept().await?;
+                    for tick in 0..count {
+                        tokio::select! {
+                            _ = tokio::time::sleep(Duration::from_millis(100)) => {}
+                            _ = sink.closed() => break,
+                        }
+                        sink.send(jsonrpsee::SubscriptionMessage::from_json(&tick)?).await?;
+                    }
+                    jsonrpsee::core::SubscriptionResult::Ok(())
+                },
+            )
+            .unwrap();
+
+        ServerConfig {
+            name: "JSON-RPC".to_string(),
+            addrs: vec![SocketAddr::from(([127, 0, 0, 1], 0))],
+            uds_path: None,
+            cors: None,
+            rpc_version_default: mp_chain_config::RpcVersion::RPC_VERSION_0_8_0,
+            rpc_versions_supported: mp_chain_config::SUPPORTED_RPC_VERSIONS_USER,
+            max_connections: 100,
+            max_subs_per_conn: 16,
+            max_payload_in_mib: 1,
+            max_payload_out_mib: 1,
+            metrics: RpcMetrics::register().unwrap(),
+            slow_request_threshold: None,
+            connection_idle_timeout: None,
+            compression: false,
+            lenient_ids: false,
+            notification_methods: &["madara_notify"],
+            message_buffer_capacity: 64,
+            methods: module.into(),
+            batch_config: jsonrpsee::server::BatchRequestConfig::Unlimited,
+            sync_phase_recv: None,
+        }
+    }
+
+    /// Serves `config` in the background, returning the address of its first listener.
+    async fn start_test_server(config: ServerConfig) -> (SocketAddr, jsonrpsee::server::ServerHandle) {
+        let listeners = ServerListeners::bind(&config).await.unwrap();
+        let addr = listeners.local_addrs()[0];
+        let (stop_handle, server_handle) = jsonrpsee::server::stop_channel();
+        tokio::spawn(serve(config, listeners, ServiceContext::new(), stop_handle, test_starknet()));
+        (addr, server_handle)
+    }
+
+    /// Sends a raw http request, returning the response headers and body.
+    async fn send(addr: SocketAddr, request: String) -> (String, Vec<u8>) {
+        send_over(tokio::net::TcpStream::connect(addr).await.unwrap(), request).await
+    }
+
+    async fn send_over(
+        mut stream: impl tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+        request: String,
+    ) -> (String, Vec<u8>) {
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = vec![];
+        stream.read_to_end(&mut response).await.unwrap();
+
+        let header_end = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        let body = response.split_off(header_end);
+        (String::from_utf8(response).unwrap(), body)
+    }
+
+    /// A json http request making the given call.
+    fn json_request(body: &str) -> String {
+        format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+    }
+
+    async fn post_json(addr: SocketAddr, body: &str) -> String {
+        let (headers, body) = send(addr, json_request(body)).await;
+        assert!(headers.starts_with("HTTP/1.1 200 OK"), "{headers}");
+        String::from_utf8(body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn serves_unix_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rpc.sock");
+        let config = ServerConfig { uds_path: Some(path.clone()), ..test_config() };
+
+        let listeners = ServerListeners::bind(&config).await.unwrap();
+        let (stop_handle, server_handle) = jsonrpsee::server::stop_channel();
+        let server = tokio::spawn(serve(config, listeners, ServiceContext::new(), stop_handle, test_starknet()));
+
+        let body = r#"{"jsonrpc":"2.0","method":"madara_echo","params":[],"id":1}"#;
+        let stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+        let (headers, body) = send_over(stream, json_request(body)).await;
+        assert!(headers.starts_with("HTTP/1.1 200 OK"), "{headers}");
+        assert_eq!(String::from_utf8(body).unwrap(), r#"{"jsonrpc":"2.0","result":"madara","id":1}"#);
+
+        let stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+        let request = "GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n".to_string();
+        let (headers, body) = send_over(stream, request).await;
+        assert!(headers.starts_with("HTTP/1.1 200 OK"), "{headers}");
+        assert_eq!(body, b"OK");
+
+        // The socket is removed once the server has stopped.
+        server_handle.stop().unwrap();
+        server.await.unwrap().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn replaces_stale_unix_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rpc.sock");
+        // A listener dropped without removing its socket, as after a crash.
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let config = ServerConfig { uds_path: Some(path.clone()), ..test_config() };
+        let _listeners = ServerListeners::bind(&config).await.unwrap();
+        tokio::net::UnixStream::connect(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn does_not_replace_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rpc.sock");
+        std::fs::write(&path, "not a socket").unwrap();
+
+        let config = ServerConfig { uds_path: Some(path.clone()), ..test_config() };
+        let err = ServerListeners::bind(&config).await.err().unwrap();
+        assert!(format!("{err:#}").contains("not a unix socket"), "{err:#}");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "not a socket");
+    }
+
+    #[tokio::test]
+    async fn compresses_http_responses() {
+        let (addr, _server_handle) = start_test_server(ServerConfig { compression: true, ..test_config() }).await;
+
+        let body = r#"{"jsonrpc":"2.0","method":"madara_large","params":[],"id":1}"#;
+        let request = format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+             Accept-Encoding: gzip\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        let (headers, body) = send(addr, request).await;
+        let headers = headers.to_lowercase();
+
+        assert!(headers.starts_with("http/1.1 200 ok"), "{headers}");
+        assert!(headers.contains("content-encoding: gzip"), "{headers}");
+        // Gzip magic bytes, and a body much smaller than the uncompressed 60KB result.
+        assert_eq!(&body[..2], &[0x1f, 0x8b]);
+        assert!(body.len() < 10_000, "{}", body.len());
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_http_requests() {
+        for (lenient_ids, compression) in [(false, false), (true, false), (false, true), (true, true)] {
+            let (addr, _server_handle) =
+                start_test_server(ServerConfig { lenient_ids, compression, ..test_config() }).await;
+
+            // The body is never read: only a fraction of it is even sent.
+            let request = format!(
+                "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\
+                 Connection: close\r\n\r\nnope",
+                100 * MiB
+            );
+            let (headers, _) = tokio::time::timeout(Duration::from_secs(5), send(addr, request))
+                .await
+                .expect("The request should be rejected without waiting for its body");
+            assert!(
+                headers.starts_with("HTTP/1.1 415 Unsupported Media Type"),
+                "lenient_ids={lenient_ids} compression={compression}: {headers}"
+            );
+
+            let request = "PUT / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n".to_string();
+            let (headers, _) = send(addr, request).await;
+            assert!(
+                headers.starts_with("HTTP/1.1 405 Method Not Allowed"),
+                "lenient_ids={lenient_ids} compression={compression}: {headers}"
+            );
+
+            let request = "GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n".to_string();
+            let (headers, _) = send(addr, request).await;
+            assert!(
+                headers.starts_with("HTTP/1.1 200 OK"),
+                "lenient_ids={lenient_ids} compression={compression}: {headers}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn ready_once_sync_is_at_tip() {
+        let (sender, sync_phase_recv) = tokio::sync::watch::channel(None);
+        let (addr, _server_handle) =
+            start_test_server(ServerConfig { sync_phase_recv: Some(sync_phase_recv), ..test_config() }).await;
+        let ready = || async move {
+            let request = "GET /ready HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n".to_string();
+            send(addr, request).await.0
+        };
+
+        // The sync target is not known yet.
+        assert!(ready().await.starts_with("HTTP/1.1 503 Service Unavailable"));
+
+        sender.send_replace(Some(SyncPhase::CatchingUp { behind: 3 }));
+        assert!(ready().await.starts_with("HTTP/1.1 503 Service Unavailable"));
+
+        sender.send_replace(Some(SyncPhase::AtTip));
+        assert!(ready().await.starts_with("HTTP/1.1 200 OK"));
+
+        sender.send_replace(Some(SyncPhase::CatchingUp { behind: 1 }));
+        assert!(ready().await.starts_with("HTTP/1.1 503 Service Unavailable"));
+    }
+
+    #[tokio::test]
+    async fn max_connections_shared_by_listeners() {
+        let localhost = SocketAddr::from(([127, 0, 0, 1], 0));
+        let config = ServerConfig { addrs: vec![localhost, localhost], max_connections: 1, ..test_config() };
+        let listeners = ServerListeners::bind(&config).await.unwrap();
+        let addrs = listeners.local_addrs();
+        let (stop_handle, _server_handle) = jsonrpsee::server::stop_channel();
+        tokio::spawn(serve(config, listeners, ServiceContext::new(), stop_handle, test_starknet()));
+
+        // A websocket session holds its connection for as long as it is open.
+        let client =
+            jsonrpsee::ws_client::WsClientBuilder::default().build(format!("ws://{}", addrs[0])).await.unwrap();
+        let response: String = client.request("madara_echo", jsonrpsee::rpc_params![]).await.unwrap();
+        assert_eq!(response, "madara");
+
+        let body = r#"{"jsonrpc":"2.0","method":"madara_echo","params":[],"id":1}"#;
+        let (headers, _) = send(addrs[1], json_request(body)).await;
+        assert!(headers.starts_with("HTTP/1.1 429 Too Many Requests"), "{headers}");
+
+        // Health checks are answered regardless of the connection limit.
+        let request = "GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n".to_string();
+        let (headers, _) = send(addrs[1], request).await;
+        assert!(headers.starts_with("HTTP/1.1 200 OK"), "{headers}");
+
+        drop(client);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if send(addrs[1], json_request(body)).await.0.starts_with("HTTP/1.1 200 OK") {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("The connection should be released once the websocket session is closed");
+    }
+
+    /// A bare websocket client, to observe the frames sent by the server.
+    struct WsClient(tokio::net::TcpStream);
+
+    impl WsClient {
+        async fn connect(addr: SocketAddr) -> Self {
+            let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(
+                    b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n\
+                      Sec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+                )
+                .await
+                .unwrap();
+
+            let mut headers = vec![];
+            while !headers.ends_with(b"\r\n\r\n") {
+                headers.push(stream.read_u8().await.unwrap());
+            }
+            assert!(headers.starts_with(b"HTTP/1.1 101"), "{}", String::from_utf8_lossy(&headers));
+            Self(stream)
+        }
+
+        /// Sends a text frame. Clients must mask their frames, a zero mask leaves the payload as is.
+        async fn send(&mut self, text: &str) {
+            let mut frame = vec![0x81, 0x80 | 126];
+            frame.extend((text.len() as u16).to_be_bytes());
+            frame.extend([0; 4]);
+            frame.extend(text.as_bytes());
+            self.0.write_all(&frame).await.unwrap();
+        }
+
+        /// Receives the opcode and payload of the next frame, skipping the pings of the server.
+        async fn recv(&mut self) -> (u8, String) {
+            tokio::time::timeout(Duration::from_secs(5), async {
+                loop {
+                    let opcode = self.0.read_u8().await.unwrap() & 0x0f;
+                    let len = match self.0.read_u8().await.unwrap() & 0x7f {
+                        126 => self.0.read_u16().await.unwrap() as usize,
+                        127 => self.0.read_u64().await.unwrap() as usize,
+                        len => len as usize,
+                    };
+                    let mut payload = vec![0; len];
+                    self.0.read_exact(&mut payload).await.unwrap();
+                    if opcode != 0x9 {
+                        break (opcode, String::from_utf8_lossy(&payload).into_owned());
+                    }
+                }
+            })
+            .await
+            .expect("Timed out waiting for a websocket frame")
+        }
+
+        /// Receives text frames until the server closes the session, returning their payloads.
+        async fn recv_until_close(&mut self) -> Vec<String> {
+            let mut texts = vec![];
+            loop {
+                match self.recv().await {
+                    (0x1, text) => texts.push(text),
+                    (0x8, _) => return texts,
+                    (opcode, _) => panic!("Unexpected websocket frame: {opcode:#x}"),
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn subscription_ended_by_server_keeps_websocket_open() {
+        let config = ServerConfig { connection_idle_timeout: Some(Duration::from_millis(300)), ..test_config() };
+        let metrics = config.metrics.clone();
+        let (addr, _server_handle) = start_test_server(config).await;
+
+        // The subscription runs for 800ms, well past the idle timeout.
+        let mut ws = WsClient::connect(addr).await;
+        ws.send(r#"{"jsonrpc":"2.0","method":"madara_subscribeTicks","params":[8],"id":1}"#).await;
+
+        // Once the server has ended the subscription, the idle session is closed with a close frame.
+        let texts = ws.recv_until_close().await;
+        assert_eq!(texts.iter().filter(|text| text.contains("madara_tick")).count(), 8, "{texts:?}");
+        assert_eq!(metrics.subscriptions_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn subscription_ended_by_client_keeps_websocket_open() {
+        let config = ServerConfig { connection_idle_timeout: Some(Duration::from_millis(300)), ..test_config() };
+        let metrics = config.metrics.clone();
+        let (addr, _server_handle) = start_test_server(config).await;
+
+        let mut ws = WsClient::connect(addr).await;
+        ws.send(r#"{"jsonrpc":"2.0","method":"madara_subscribeTicks","params":[1000],"id":1}"#).await;
+        let (_, subscribed) = ws.recv().await;
+        let subscription_id = serde_json::from_str::<serde_json::Value>(&subscribed).unwrap()["result"].clone();
+        for _ in 0..5 {
+            assert!(ws.recv().await.1.contains("madara_tick"));
+        }
+        assert_eq!(metrics.subscriptions_count(), 1);
+
+        ws.send(&format!(
+            r#"{{"jsonrpc":"2.0","method":"madara_unsubscribeTicks","params":[{subscription_id}],"id":2}}"#
+        ))
+        .await;
+        let texts = ws.recv_until_close().await;
+        assert!(texts.iter().any(|text| text == r#"{"jsonrpc":"2.0","result":true,"id":2}"#), "{texts:?}");
+        assert_eq!(metrics.subscriptions_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn subscriptions_tracked_per_connection() {
+        let config = ServerConfig { connection_idle_timeout: Some(Duration::from_millis(300)), ..test_config() };
+        let metrics = config.metrics.clone();
+        let (addr, _server_handle) = start_test_server(config).await;
+
+        let mut subscribed = WsClient::connect(addr).await;
+        subscribed.send(r#"{"jsonrpc":"2.0","method":"madara_subscribeTicks","params":[1000],"id":1}"#).await;
+        let mut idle = WsClient::connect(addr).await;
+
+        // Only the connection without a subscription is closed as idle.
+        assert_eq!(idle.recv_until_close().await, Vec::<String>::new());
+        assert!(subscribed.recv().await.1.contains(r#""id":1"#));
+        assert!(subscribed.recv().await.1.contains("madara_tick"));
+        assert_eq!(metrics.subscriptions_count(), 1);
+
+        // The subscription ends along with its connection.
+        drop(subscribed);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while metrics.subscriptions_count() != 0 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("The subscription was not released");
+    }
+
+    #[tokio::test]
+    async fn closes_idle_http_keep_alive_connections() {
+        let timeout = Duration::from_millis(300);
+        let (addr, _server_handle) =
+            start_test_server(ServerConfig { connection_idle_timeout: Some(timeout), ..test_config() }).await;
+
+        let body = r#"{"jsonrpc":"2.0","method":"madara_echo","params":[],"id":1}"#;
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                format!(
+                    "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\n\r\n{body}",
+                    body.len()
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+
+        let mut response = vec![];
+        while !response.ends_with(br#"{"jsonrpc":"2.0","result":"madara","id":1}"#) {
+            response.push(stream.read_u8().await.unwrap());
+        }
+        let answered = std::time::Instant::now();
+
+        // The connection is kept alive until it has been idle for long enough.
+        let mut buf = [0; 16];
+        assert_eq!(tokio::time::timeout(timeout * 5, stream.read(&mut buf)).await.unwrap().unwrap(), 0);
+        assert!(answered.elapsed() >= timeout, "{:?}", answered.elapsed());
+    }
+
+    #[tokio::test]
+    async fn strict_ids() {
+        let (addr, _server_handle) = start_test_server(test_config()).await;
+
+        let response = post_json(addr, r#"{"jsonrpc":"2.0","method":"madara_echo","params":[],"id":1}"#).await;
+        assert_eq!(response, r#"{"jsonrpc":"2.0","result":"madara","id":1}"#);
+        let response = post_json(addr, r#"{"jsonrpc":"2.0","method":"madara_echo","params":[],"id":"a"}"#).await;
+        assert_eq!(response, r#"{"jsonrpc":"2.0","result":"madara","id":"a"}"#);
+        // Calls without an id are notifications, which are never answered.
+        let response = post_json(addr, r#"{"jsonrpc":"2.0","method":"madara_echo","params":[]}"#).await;
+        assert_eq!(response, "");
+    }
+
+    #[tokio::test]
+    async fn lenient_ids() {
+        let (addr, _server_handle) = start_test_server(ServerConfig { lenient_ids: true, ..test_config() }).await;
+
+        let response = post_json(addr, r#"{"jsonrpc":"2.0","method":"madara_echo","params":[],"id":1}"#).await;
+        assert_eq!(response, r#"{"jsonrpc":"2.0","result":"madara","id":1}"#);
+        let response = post_json(addr, r#"{"jsonrpc":"2.0","method":"madara_echo","params":[],"id":"a"}"#).await;
+        assert_eq!(response, r#"{"jsonrpc":"2.0","result":"madara","id":"a"}"#);
+        let response = post_json(addr, r#"{"jsonrpc":"2.0","method":"madara_echo","params":[]}"#).await;
+        assert_eq!(response, r#"{"jsonrpc":"2.0","result":"madara","id":null}"#);
+        // Notification methods are still not answered.
+        let response = post_json(addr, r#"{"jsonrpc":"2.0","method":"madara_notify","params":[]}"#).await;
+        assert_eq!(response, "");
+
+        let response = post_json(
+            addr,
+            r#"[{"jsonrpc":"2.0","method":"madara_echo","params":[],"id":1},{"jsonrpc":"2.0","method":"madara_echo","params":[]}]"#,
+        )
+        .await;
+        assert_eq!(
+            response,
+            r#"[{"jsonrpc":"2.0","result":"madara","id":1},{"jsonrpc":"2.0","result":"madara","id":null}]"#
+        );
+    }
+
+    #[test]
+    fn add_missing_ids_only_rewrites_calls_without_id() {
+        let notification_methods = &["madara_notify"];
+        assert_eq!(add_missing_ids(br#"{"jsonrpc":"2.0","method":"madara_echo","id":1}"#, notification_methods), None);
+        assert_eq!(
+            add_missing_ids(br#"{"jsonrpc":"2.0","method":"madara_echo","id":"a"}"#, notification_methods),
+            None
+        );
+        assert_eq!(add_missing_ids(br#"{"jsonrpc":"2.0","result":"madara"}"#, notification_methods), None);
+        assert_eq!(add_missing_ids(br#"{"jsonrpc":"2.0","method":"madara_notify"}"#, notification_methods), None);
+        assert_eq!(
+            add_missing_ids(br#"{"jsonrpc":"2.0","method":"madara_V0_1_0_notify"}"#, notification_methods),
+            None
+        );
+        assert_eq!(add_missing_ids(b"nope", notification_methods), None);
+
+        let rewritten = add_missing_ids(
+            br#"[{"jsonrpc":"2.0","method":"madara_echo","id":1},{"jsonrpc":"2.0","method":"madara_echo"},{"jsonrpc":"2.0","method":"madara_notify"}]"#,
+            notification_methods,
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&rewritten).unwrap(),
+            serde_json::json!([
+                { "jsonrpc": "2.0", "method": "madara_echo", "id": 1 },
+                { "jsonrpc": "2.0", "method": "madara_echo", "id": null },
+                { "jsonrpc": "2.0", "method": "madara_notify" },
+            ])
+        );
+    }
+}
@@ -21,4 +21,9 @@ pub struct ServiceCliArgs {
     /// The maximum number of proving jobs to process concurrently.
     #[arg(env = "MADARA_ORCHESTRATOR_MAX_CONCURRENT_PROVING_JOBS", long)]
     pub max_concurrent_proving_jobs: Option<usize>,
+
+    /// Identical worker triggers received within this many seconds of the last processed one are dropped. 0 disables
+    /// deduplication.
+    #[arg(env = "MADARA_ORCHESTRATOR_WORKER_TRIGGER_DEDUP_WINDOW_SECONDS", long, default_value = "0")]
+    pub worker_trigger_dedup_window_seconds: u64,
 }
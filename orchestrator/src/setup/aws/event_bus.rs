@@ -55,19 +55,7 @@ impl Resource for InnerAWSEventBridge {
     }
 
     async fn setup(&self, layer: &Layer, args: Self::SetupArgs) -> OrchestratorResult<Self::SetupResult> {
-        // TODO: ideally this should also be created after check_if_exists returns false
-        let trigger_arns = self
-            .create_cron(&args.target_queue_identifier, &args.trigger_role_name, &args.trigger_policy_name)
-            .await
-            .map_err(|e| {
-                OrchestratorError::SetupCommandError(format!(
-                    "Failed to create cron: {:?} for queue: {:?}",
-                    e,
-                    args.target_queue_identifier.clone()
-                ))
-            })?;
-        sleep(Duration::from_secs(15)).await;
-
+        let mut missing_triggers = Vec::new();
         for trigger in WORKER_TRIGGERS.iter() {
             // Proof registration is only required in L3
             // TODO: Remove this once we have handle the pipeline with state machine
@@ -84,17 +72,39 @@ impl Resource for InnerAWSEventBridge {
             {
                 tracing::info!(" ⏭️ Event Bridge {trigger} already exists, skipping");
             } else {
-                self.add_cron_target_queue(
-                    trigger,
-                    &trigger_arns,
-                    args.trigger_rule_template_name.clone(),
-                    args.event_bridge_type.clone(),
-                    Duration::from_secs(args.cron_time),
-                )
-                .await
-                .expect("Failed to add Event Bus target queue");
+                missing_triggers.push(trigger);
             }
         }
+
+        // The role and policy are only used by the triggers: do not create new ones on every run once all the
+        // triggers exist.
+        if missing_triggers.is_empty() {
+            return Ok(());
+        }
+
+        let trigger_arns = self
+            .create_cron(&args.target_queue_identifier, &args.trigger_role_name, &args.trigger_policy_name)
+            .await
+            .map_err(|e| {
+                OrchestratorError::SetupCommandError(format!(
+                    "Failed to create cron: {:?} for queue: {:?}",
+                    e,
+                    args.target_queue_identifier.clone()
+                ))
+            })?;
+        sleep(Duration::from_secs(15)).await;
+
+        for trigger in missing_triggers {
+            self.add_cron_target_queue(
+                trigger,
+                &trigger_arns,
+                args.trigger_rule_template_name.clone(),
+                args.event_bridge_type.clone(),
+                Duration::from_secs(args.cron_time),
+            )
+            .await
+            .expect("Failed to add Event Bus target queue");
+        }
         Ok(())
     }
 
@@ -1,6 +1,7 @@
 use std::net::SocketAddr;
 use std::str::FromStr as _;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::core::client::database::MockDatabaseClient;
 use crate::core::client::queue::MockQueueClient;
@@ -553,12 +554,17 @@ pub(crate) fn get_env_params() -> EnvParams {
     let max_concurrent_created_snos_jobs: u64 =
         env_value.parse::<u64>().expect("Invalid number format for max concurrent SNOS jobs");
 
+    let env_value: String = get_env_var_or_default("MADARA_ORCHESTRATOR_WORKER_TRIGGER_DEDUP_WINDOW_SECONDS", "0");
+    let worker_trigger_dedup_window =
+        Duration::from_secs(env_value.parse::<u64>().expect("Invalid number format for worker trigger dedup window"));
+
     let service_config = ServiceParams {
         max_block_to_process: max_block,
         min_block_to_process: min_block,
         max_concurrent_created_snos_jobs,
         max_concurrent_snos_jobs,
         max_concurrent_proving_jobs,
+        worker_trigger_dedup_window,
     };
 
     let server_config = ServerParams {
@@ -5,6 +5,8 @@ pub mod jobs;
 
 pub mod server;
 
+pub mod setup;
+
 pub mod queue;
 
 pub mod alerts;
@@ -0,0 +1,45 @@
+use crate::cli::cron::event_bridge::EventBridgeType;
+use crate::cli::Layer;
+use crate::core::client::event_bus::event_bridge::InnerAWSEventBridge;
+use crate::core::client::queue::sqs::InnerSQS;
+use crate::core::traits::resource::Resource;
+use crate::tests::config::{ConfigType, TestConfigBuilder};
+use crate::types::params::{AWSResourceIdentifier, CronArgs};
+use crate::types::queue::QueueType;
+use orchestrator_utils::env_utils::get_env_var_or_panic;
+use rstest::rstest;
+use uuid::Uuid;
+
+async fn count_roles(event_bridge: &InnerAWSEventBridge, role_name_prefix: &str) -> usize {
+    let roles = event_bridge.iam_client.list_roles().send().await.unwrap();
+    roles.roles().iter().filter(|role| role.role_name().starts_with(role_name_prefix)).count()
+}
+
+/// Running the setup again once every trigger exists succeeds, without provisioning anything new.
+#[rstest]
+#[tokio::test]
+async fn event_bridge_setup_twice_is_a_noop() {
+    // Building the config creates the queues targeted by the triggers.
+    let services = TestConfigBuilder::new().configure_queue_client(ConfigType::Actual).build().await;
+
+    let queue_name = InnerSQS::get_queue_name_from_type(
+        &get_env_var_or_panic("MADARA_ORCHESTRATOR_AWS_SQS_QUEUE_IDENTIFIER"),
+        &QueueType::WorkerTrigger,
+    );
+    let id = Uuid::new_v4();
+    let args = CronArgs {
+        target_queue_identifier: AWSResourceIdentifier::Name(queue_name),
+        event_bridge_type: EventBridgeType::Rule,
+        cron_time: 60,
+        trigger_rule_template_name: format!("mo-setup-test-{id}-rule"),
+        trigger_role_name: format!("mo-setup-test-{id}-role"),
+        trigger_policy_name: format!("mo-setup-test-{id}-policy"),
+    };
+
+    let event_bridge = InnerAWSEventBridge::create_setup(services.provider_config.clone()).await.unwrap();
+    event_bridge.setup(&Layer::L2, args.clone()).await.unwrap();
+    assert_eq!(count_roles(&event_bridge, &args.trigger_role_name).await, 1);
+
+    event_bridge.setup(&Layer::L2, args.clone()).await.unwrap();
+    assert_eq!(count_roles(&event_bridge, &args.trigger_role_name).await, 1);
+}
@@ -10,7 +10,7 @@ use std::str::FromStr;
 use strum_macros::Display;
 use thiserror::Error;
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Display, strum_macros::EnumString)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Display, strum_macros::EnumString)]
 #[strum(serialize_all = "PascalCase")]
 pub enum WorkerTriggerType {
     Snos,
@@ -1,5 +1,6 @@
 use crate::cli::server::ServerCliArgs;
 use crate::cli::service::ServiceCliArgs;
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct ServiceParams {
@@ -8,6 +9,7 @@ pub struct ServiceParams {
     pub max_concurrent_created_snos_jobs: u64,
     pub max_concurrent_snos_jobs: Option<usize>,
     pub max_concurrent_proving_jobs: Option<usize>,
+    pub worker_trigger_dedup_window: Duration,
 }
 
 impl From<ServiceCliArgs> for ServiceParams {
@@ -18,6 +20,7 @@ impl From<ServiceCliArgs> for ServiceParams {
             max_concurrent_created_snos_jobs: args.max_concurrent_created_snos_jobs,
             max_concurrent_snos_jobs: args.max_concurrent_snos_jobs,
             max_concurrent_proving_jobs: args.max_concurrent_proving_jobs,
+            worker_trigger_dedup_window: Duration::from_secs(args.worker_trigger_dedup_window_seconds),
         }
     }
 }
@@ -4,6 +4,7 @@ use crate::error::{
     event::{EventSystemError, EventSystemResult},
     ConsumptionError,
 };
+use crate::types::jobs::WorkerTriggerType;
 use crate::types::queue::{JobState, QueueType};
 use crate::worker::event_handler::service::JobHandlerService;
 use crate::worker::parser::{job_queue_message::JobQueueMessage, worker_trigger_message::WorkerTriggerMessage};
@@ -11,8 +12,9 @@ use crate::worker::traits::message::{MessageParser, ParsedMessage};
 use color_eyre::eyre::eyre;
 use omniqueue::backends::SqsConsumer;
 use omniqueue::{Delivery, QueueError};
-use std::sync::Arc;
-use std::time::Duration;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
 use tracing::{debug, error, info, info_span};
 
@@ -21,9 +23,36 @@ pub enum MessageType {
     NoMessage,
 }
 
+/// Collapses identical worker triggers: a trigger is a duplicate when a trigger of the same type was processed less
+/// than `window` ago. A zero window disables deduplication.
+pub struct TriggerDeduplicator {
+    window: Duration,
+    last_processed: Mutex<HashMap<WorkerTriggerType, Instant>>,
+}
+
+impl TriggerDeduplicator {
+    pub fn new(window: Duration) -> Self {
+        Self { window, last_processed: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn is_duplicate(&self, worker: &WorkerTriggerType, now: Instant) -> bool {
+        if self.window.is_zero() {
+            return false;
+        }
+        let last_processed = self.last_processed.lock().expect("Poisoned lock");
+        last_processed.get(worker).is_some_and(|at| now.saturating_duration_since(*at) < self.window)
+    }
+
+    /// Only successful runs are recorded, so that a failed trigger can be retried within the window.
+    pub fn record(&self, worker: &WorkerTriggerType, now: Instant) {
+        self.last_processed.lock().expect("Poisoned lock").insert(worker.clone(), now);
+    }
+}
+
 pub struct EventWorker {
     queue_type: QueueType,
     config: Arc<Config>,
+    trigger_deduplicator: TriggerDeduplicator,
 }
 
 impl EventWorker {
@@ -36,7 +65,8 @@ impl EventWorker {
     /// * `EventWorker` - A new EventWorker instance
     pub fn new(queue_type: QueueType, config: Arc<Config>) -> Self {
         info!("Kicking in the Worker to Monitor the Queue {:?}", queue_type);
-        Self { queue_type, config }
+        let trigger_deduplicator = TriggerDeduplicator::new(config.service_config().worker_trigger_dedup_window);
+        Self { queue_type, config, trigger_deduplicator }
     }
 
     async fn consumer(&self) -> EventSystemResult<SqsConsumer> {
@@ -90,12 +120,17 @@ impl EventWorker {
                 if let ParsedMessage::WorkerTrigger(worker_mes) = message {
                     let span = info_span!("worker_trigger", q = %self.queue_type, worker_id = %worker_mes.worker);
                     let _guard = span.enter();
+                    if self.trigger_deduplicator.is_duplicate(&worker_mes.worker, Instant::now()) {
+                        info!("Skipping {} trigger received within the deduplication window", worker_mes.worker);
+                        return Ok(());
+                    }
                     let worker_handler =
                         JobHandlerService::get_worker_handler_from_worker_trigger_type(worker_mes.worker.clone());
                     worker_handler
                         .run_worker_if_enabled(self.config.clone())
                         .await
                         .map_err(|e| ConsumptionError::Other(OtherError::from(e.to_string())))?;
+                    self.trigger_deduplicator.record(&worker_mes.worker, Instant::now());
                     Ok(())
                 } else {
                     Err(EventSystemError::from(ConsumptionError::Other(OtherError::from(eyre!(
@@ -260,3 +295,37 @@ impl EventWorker {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trigger_deduplicator_collapses_triggers_within_window() {
+        let deduplicator = TriggerDeduplicator::new(Duration::from_secs(10));
+        let start = Instant::now();
+
+        let dispatched = (0..3)
+            .map(|i| start + Duration::from_secs(i))
+            .filter(|&now| {
+                let dispatch = !deduplicator.is_duplicate(&WorkerTriggerType::Proving, now);
+                if dispatch {
+                    deduplicator.record(&WorkerTriggerType::Proving, now);
+                }
+                dispatch
+            })
+            .count();
+        assert_eq!(dispatched, 1);
+
+        assert!(!deduplicator.is_duplicate(&WorkerTriggerType::DataSubmission, start));
+        assert!(!deduplicator.is_duplicate(&WorkerTriggerType::Proving, start + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_trigger_deduplicator_zero_window_is_disabled() {
+        let deduplicator = TriggerDeduplicator::new(Duration::ZERO);
+        let now = Instant::now();
+        deduplicator.record(&WorkerTriggerType::Proving, now);
+        assert!(!deduplicator.is_duplicate(&WorkerTriggerType::Proving, now));
+    }
+}
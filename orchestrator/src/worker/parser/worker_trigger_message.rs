@@ -3,7 +3,7 @@ use crate::error::other::OtherError;
 use crate::error::ConsumptionError;
 use crate::types::jobs::WorkerTriggerType;
 use crate::worker::traits::message::MessageParser;
-use color_eyre::eyre::Context;
+use color_eyre::eyre::{eyre, Context};
 use omniqueue::Delivery;
 use serde::Serialize;
 use std::str::FromStr;
@@ -18,10 +18,73 @@ impl MessageParser for WorkerTriggerMessage {
         let payload = message
             .borrow_payload()
             .ok_or_else(|| ConsumptionError::Other(OtherError::from("Empty payload".to_string())))?;
-        let message_string = String::from_utf8_lossy(payload).to_string().trim_matches('\"').to_string();
-        let trigger_type = WorkerTriggerType::from_str(message_string.as_str())
+        let trigger_type = parse_body(&String::from_utf8_lossy(payload))
             .wrap_err("Failed to parse worker trigger type from message")
             .map_err(|e| ConsumptionError::Other(OtherError::from(e)))?;
         Ok(Box::new(Self { worker: trigger_type }))
     }
 }
+
+/// Parses a trigger published through SNS. With raw message delivery the body is the trigger itself, otherwise it is
+/// wrapped in an SNS envelope whose `Message` field holds the trigger as a string.
+fn parse_body(body: &str) -> color_eyre::Result<WorkerTriggerType> {
+    let envelope = serde_json::from_str::<serde_json::Value>(body).ok();
+    match envelope.as_ref().and_then(|envelope| envelope.get("Message")).and_then(serde_json::Value::as_str) {
+        Some(message) => parse_trigger(message),
+        None => parse_trigger(body),
+    }
+}
+
+/// Parses a trigger, published either as the bare trigger type (`Proving`), as a JSON string (`"Proving"`) or as a
+/// JSON object (`{"worker":"Proving"}`).
+fn parse_trigger(trigger: &str) -> color_eyre::Result<WorkerTriggerType> {
+    let trigger = trigger.trim();
+    let worker = match serde_json::from_str::<serde_json::Value>(trigger) {
+        Ok(serde_json::Value::String(worker)) => worker,
+        Ok(serde_json::Value::Object(object)) => object
+            .get("worker")
+            .and_then(serde_json::Value::as_str)
+            .ok_or_else(|| eyre!("Missing worker in trigger: {trigger}"))?
+            .to_string(),
+        _ => trigger.to_string(),
+    };
+    WorkerTriggerType::from_str(&worker).wrap_err_with(|| format!("Unknown worker trigger type: {worker}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use omniqueue::backends::InMemoryBackend;
+    use rstest::rstest;
+
+    async fn deliver(body: &str) -> Delivery {
+        let (producer, mut consumer) = InMemoryBackend::builder().build_pair().await.unwrap();
+        producer.send_raw(body.as_bytes()).await.unwrap();
+        consumer.receive().await.unwrap()
+    }
+
+    #[rstest]
+    #[case::bare("Proving")]
+    #[case::string(r#""Proving""#)]
+    #[case::object(r#"{"worker":"Proving"}"#)]
+    #[case::envelope_bare(
+        r#"{"Type":"Notification","MessageId":"6b1c2b0e","TopicArn":"arn:aws:sns:us-east-1:000000000000:worker-trigger","Message":"Proving"}"#
+    )]
+    #[case::envelope_string(r#"{"Type":"Notification","Message":"\"Proving\""}"#)]
+    #[case::envelope_object(r#"{"Type":"Notification","Message":"{\"worker\":\"Proving\"}"}"#)]
+    #[tokio::test]
+    async fn test_parse_worker_trigger_message(#[case] body: &str) {
+        let message = WorkerTriggerMessage::parse_message(&deliver(body).await).unwrap();
+        assert_eq!(message.worker, WorkerTriggerType::Proving);
+    }
+
+    #[rstest]
+    #[case::unknown_worker("Unknown")]
+    #[case::unknown_worker_in_object(r#"{"worker":"Unknown"}"#)]
+    #[case::unknown_worker_in_envelope(r#"{"Message":"{\"worker\":\"Unknown\"}"}"#)]
+    #[case::missing_worker(r#"{"job":"Proving"}"#)]
+    #[tokio::test]
+    async fn test_parse_worker_trigger_message_fails(#[case] body: &str) {
+        assert!(WorkerTriggerMessage::parse_message(&deliver(body).await).is_err());
+    }
+}